@@ -19,10 +19,38 @@ pub(crate) fn build_float(
     };
 
     let mut bit_size: Option<(i32, i32)> = None;
+    let mut plain_bits: Option<u8> = None;
+    let mut range: Option<(f64, f64)> = None;
+    let mut precision: Option<f64> = None;
     let mut sub_bits = vec![];
     flags |= decode_flags(attrs);
     for attr in attrs {
         match attr.interpret_meta().unwrap() {
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_bits" && !val.value().contains(':') => {
+                let val = val.value();
+                let bits: u8 = val.trim().parse().expect("Invalid bit count");
+                if bits == 0 || bits > 53 {
+                    panic!("Wanted {} bits but a range can use between 1 and 53", bits)
+                }
+                plain_bits = Some(bits);
+            },
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_range" => {
+                let val = val.value();
+                let mut parts = val.splitn(2, "..");
+                let min: f64 = parts.next().unwrap().trim().parse().expect("Invalid range start");
+                let max: f64 = parts.next().expect("Range must be `min..max`").trim().parse().expect("Invalid range end");
+                if !(max > min) {
+                    panic!("Range {} is empty", val)
+                }
+                range = Some((min, max));
+            },
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_precision" => {
+                let val: f64 = val.value().trim().parse().expect("Invalid precision");
+                if !(val > 0.0) {
+                    panic!("Precision must be positive")
+                }
+                precision = Some(val);
+            },
             syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_bits" => {
                 let val = val.value();
                 let mut parts = val.split(":");
@@ -61,7 +89,74 @@ pub(crate) fn build_float(
         (syn::Ident::new("write_f64", Span::call_site()), syn::Ident::new("read_f64", Span::call_site()))
     };
 
-    if flags.contains(GenFlags::FIXED) {
+    if range.is_none() && plain_bits.is_some() {
+        panic!("`delta_bits` on a float requires either `delta_range` or `int:fract` bits with `delta_fixed`")
+    }
+
+    if let Some((min, max)) = range {
+        let bits = match (plain_bits, precision) {
+            (Some(bits), None) => quote!(#bits),
+            (None, Some(precision)) => {
+                let msg = format!("A precision of {} over {}..{} needs more than the 53 bits a range can use", precision, min, max);
+                quote!(const {
+                    let bits = crate::delta_encode::range_bits(#min, #max, #precision);
+                    assert!(bits <= 53, #msg);
+                    bits
+                })
+            },
+            _ => panic!("`delta_range` requires either `delta_bits` or `delta_precision`"),
+        };
+        let quantized = |val: &TokenStream| quote!{
+            crate::delta_encode::quantize(#val as f64, #min, #max, #bits)
+        };
+        let (qself, qbase) = (quantized(name_self), quantized(name_base));
+        let dec = quote!{
+            crate::delta_encode::dequantize(r.read_unsigned(#bits)?, #min, #max, #bits) as #ty
+        };
+        if flags.contains(GenFlags::ALWAYS) {
+            let enc = quote!{
+                w.write_unsigned(#qself, #bits)?;
+            };
+            encode.push(enc.clone());
+            encode_part.push(enc);
+            decode.push(quote!{
+                #de_target #dec
+            });
+            decode_part.push(quote!{
+                #de_target #dec
+            });
+        } else {
+            encode.push(quote!{
+                w.write_bool(true)?;
+                w.write_unsigned(#qself, #bits)?;
+            });
+            // Values that quantize to the same step as the base are
+            // unchanged as far as the receiver can tell
+            encode_part.push(quote!{
+                let __val = #qself;
+                if __val != #qbase {
+                    w.write_bool(true)?;
+                    w.write_unsigned(__val, #bits)?;
+                } else {
+                    w.write_bool(false)?;
+                }
+            });
+            decode.push(quote!{
+                #de_target if r.read_bool()? {
+                    #dec
+                } else {
+                    return Err(crate::delta_encode::DeltaError::missing_baseline());
+                }
+            });
+            decode_part.push(quote!{
+                #de_target if r.read_bool()? {
+                    #dec
+                } else {
+                    #name_base
+                }
+            });
+        }
+    } else if flags.contains(GenFlags::FIXED) {
         if let Some((int, fract)) = bit_size {
             let bits = (int + fract) as u8;
             if flags.contains(GenFlags::ALWAYS) {
@@ -94,7 +189,7 @@ pub(crate) fn build_float(
                     #de_target if r.read_bool()? {
                         r.read_signed(#bits)? as #ty / ((1 << #fract) as #ty)
                     } else {
-                        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state"));
+                        return Err(crate::delta_encode::DeltaError::missing_baseline());
                     }
                 });
                 decode_part.push(quote!{
@@ -138,6 +233,11 @@ pub(crate) fn build_float(
                 offset += 1;
             }
             let fract = sub_bits.first().unwrap().1;
+            let max_sub_bits = sub_bits.iter()
+                .filter(|&&(int, fract)| int != -1 && fract != -1)
+                .map(|&(int, fract)| (int + fract) as u8)
+                .max()
+                .unwrap_or(64);
             for &(int, ofract) in &sub_bits {
                 if int == -1 || fract == -1 {
                     let enc = quote!(
@@ -208,14 +308,14 @@ pub(crate) fn build_float(
                     let __diff_val = __diff_val_s - __diff_val_b;
                     #(#encode_part_vals else)*
                     {
-                        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range"))
+                        return Err(crate::delta_encode::DeltaError::out_of_range(__diff_val as i128, #max_sub_bits))
                     }
                 });
                 decode_part.push(quote!{
                     #de_target {
                         match r.read_unsigned(#required_bits)? {
                             #(#decode_part_vals)*
-                            _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                            __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                         }
                     }
                 });
@@ -224,13 +324,13 @@ pub(crate) fn build_float(
                     let __diff_val = (#name_self * (1 << #fract) as #ty) as i64;
                     #(#encode_vals else)*
                     {
-                        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range"))
+                        return Err(crate::delta_encode::DeltaError::out_of_range(__diff_val as i128, #max_sub_bits))
                     }
                 });
                 decode.push(quote!{
                     #de_target match r.read_unsigned(#required_bits)? {
                         #(#decode_vals)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 });
             } else {
@@ -238,13 +338,13 @@ pub(crate) fn build_float(
                     let __abs_val = (#name_self * (1 << #fract) as #ty) as i64;
                     #(#encode_part_vals else)*
                     {
-                        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range"))
+                        return Err(crate::delta_encode::DeltaError::out_of_range(__abs_val as i128, #max_sub_bits))
                     }
                 });
                 decode_part.push(quote!{
                     #de_target match r.read_unsigned(#required_bits)? {
                         #(#decode_part_vals)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 });
 
@@ -252,13 +352,13 @@ pub(crate) fn build_float(
                     let __abs_val = (#name_self * (1 << #fract) as #ty) as i64;
                     #(#encode_vals else)*
                     {
-                        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range"))
+                        return Err(crate::delta_encode::DeltaError::out_of_range(__abs_val as i128, #max_sub_bits))
                     }
                 });
                 decode.push(quote!{
                     #de_target match r.read_unsigned(#required_bits)? {
                         #(#decode_vals)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 });
             }
@@ -297,7 +397,7 @@ pub(crate) fn build_float(
                 #de_target if r.read_bool()? {
                     r.#dmethod()?
                 } else {
-                    return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state"));
+                    return Err(crate::delta_encode::DeltaError::missing_baseline());
                 }
             });
             decode_part.push(quote!{
@@ -309,4 +409,4 @@ pub(crate) fn build_float(
             });
         }
     }
-}
\ No newline at end of file
+}
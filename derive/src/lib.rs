@@ -29,6 +29,7 @@ bitflags!{
         const DIFF     = 0b0000_0100;
         const FIXED    = 0b0000_1000;
         const DEFAULT  = 0b0001_0000;
+        const CHANGED  = 0b0010_0000;
     }
 }
 
@@ -37,8 +38,38 @@ bitflags!{
 // delta_always = always send this value instead of only changes
 // delta_complete = compare the whole struct and only send if changed
 // delta_diff = sends the difference between the values, only useful when
-//              used with `delta_subbits`
+//              used with `delta_subbits` or a variable length code
 // delta_fixed - Causes the floating point number to be sent as a fixed point number
+// delta_range = "min..max" - Quantizes a float onto the range, clamping values
+//               outside of it. Requires `delta_precision` or `delta_bits`
+// delta_precision = the largest step allowed between quantized values
+// delta_quat(bits = N) - Sends a quaternion as a rotation using the smallest
+//                        three encoding with `N` (at least 2) bits per component
+// delta_unit_vector(bits = N) - Sends a vector as a direction using an octahedral
+//                               mapping with `N` (at least 2) bits per component. Arrays
+//                               of floats are treated as a single vector
+// delta_varint - Sends an integer in groups of 7 bits, signed integers are zigzag encoded
+// delta_gamma - Sends an integer using the Elias-gamma code, cheapest for values near zero
+// delta_rice = "k" - Sends an integer using the Golomb-Rice code with parameter `k`,
+//                    cheapest for values around `2^k`
+// delta_model = "path" - Codes the field or type with the `StaticModel` at `path`
+//                        when written with a `RangeWriter` (`range_coder` feature),
+//                        see `ModelTrainer`. The model holds fixed per-bit
+//                        probabilities, not a Huffman or ANS table
+// delta_interned - Sends a string as its id in the writer's `StringTable` when it
+//                  has one, see `InternWriter`
+// delta_tag = N - The tag a variant is sent with, defaults to the tag after the
+//                 previous variant's. Tags must be unique
+// delta_tag_bits = N - The number of bits enum tags are sent with, defaults to the
+//                      fewest that fit the largest tag
+// delta_discriminant(min = A, max = B, bits = N) - Sends the variants of an enum without
+//                      fields as their discriminant minus `min` rather than their tag.
+//                      `min` and `max` default to the range of the discriminants and
+//                      `bits` to the fewest that fit it. Can't be combined with
+//                      `delta_tag` or `delta_tag_bits`
+//
+// Array fields are sent against their base as the set of changed elements,
+// finding those needs the element type to be `PartialEq` and `Clone`
 
 #[proc_macro_derive(DeltaEncode, attributes(
     delta_bits,
@@ -48,6 +79,18 @@ bitflags!{
     delta_complete,
     delta_fixed,
     delta_default,
+    delta_range,
+    delta_precision,
+    delta_quat,
+    delta_unit_vector,
+    delta_varint,
+    delta_gamma,
+    delta_rice,
+    delta_model,
+    delta_interned,
+    delta_tag,
+    delta_tag_bits,
+    delta_discriminant,
 ))]
 pub fn delta_encode(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast: syn::DeriveInput = syn::parse(input).expect("Failed to parse input");
@@ -60,6 +103,44 @@ fn delta_encode_impl(ast: syn::DeriveInput) -> TokenStream {
 
     let flags = decode_flags(&ast.attrs);
 
+    let name_str = name.to_string();
+    let (w_enter, w_leave, r_enter, r_leave) = model_hooks(&ast.attrs);
+
+    // Enums without fields start with a changed bit, which a value that
+    // is known to have changed can go without
+    let changed = match ast.data {
+        syn::Data::Enum(ref e) if !e.variants.is_empty() && e.variants.iter().all(|v| v.fields == syn::Fields::Unit) => {
+            let (enc, dec) = build_c_enum(name, flags | GenFlags::ALWAYS, &ast.attrs, &e.variants);
+            quote! {
+                #[inline]
+                fn encode_changed<W>(&self, base: &Self, w: &mut W) -> ::core::result::Result<(), crate::delta_encode::DeltaError>
+                    where W: crate::delta_encode::BitWrite
+                {
+                    #w_enter
+                    let __res = (|| {
+                        #enc
+                        Ok(())
+                    })();
+                    #w_leave
+                    __res.map_err(|e: crate::delta_encode::DeltaError| e.in_type(#name_str))
+                }
+
+                #[inline]
+                fn decode_changed<R>(base: &Self, r: &mut R) -> ::core::result::Result<Self, crate::delta_encode::DeltaError>
+                    where R: crate::delta_encode::BitRead
+                {
+                    #r_enter
+                    let __res = (|| {
+                        Ok(#dec)
+                    })();
+                    #r_leave
+                    __res.map_err(|e: crate::delta_encode::DeltaError| e.in_type(#name_str))
+                }
+            }
+        },
+        _ => quote!(),
+    };
+
     let (enc, dec) = match ast.data {
         syn::Data::Struct(syn::DataStruct{fields: syn::Fields::Named(fields), ..}) => {
             build_struct(name, &syn::Ident::new("self", Span::call_site()), &syn::Ident::new("base", Span::call_site()), flags, fields.named)
@@ -67,29 +148,44 @@ fn delta_encode_impl(ast: syn::DeriveInput) -> TokenStream {
         syn::Data::Struct(syn::DataStruct{fields: syn::Fields::Unnamed(fields), ..}) => {
             build_tuple(name, &syn::Ident::new("self", Span::call_site()), &syn::Ident::new("base", Span::call_site()), flags, fields.unnamed)
         },
+        syn::Data::Enum(ref e) if !e.variants.is_empty() && e.variants.iter().all(|v| v.fields == syn::Fields::Unit) => {
+            build_c_enum(name, flags, &ast.attrs, &e.variants)
+        },
         syn::Data::Enum(e) => {
-            build_enum(name, &syn::Ident::new("self", Span::call_site()), &syn::Ident::new("base", Span::call_site()), flags, e.variants)
+            build_enum(name, &syn::Ident::new("self", Span::call_site()), &syn::Ident::new("base", Span::call_site()), flags, &ast.attrs, e.variants)
         }
         _ => unimplemented!("body type"),
     };
 
     quote! {
-        #[allow(unused_variables, non_snake_case, unreachable_patterns, clippy::float_cmp)]
+        #[allow(unused_variables, non_snake_case, unreachable_patterns, clippy::float_cmp, clippy::needless_question_mark)]
         impl crate::delta_encode::DeltaEncodable for #name {
             #[inline]
-            fn encode<W>(&self, base: Option<&Self>, w: &mut crate::delta_encode::bitio::Writer<W>) -> ::std::io::Result<()>
-                where W: std::io::Write
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> ::core::result::Result<(), crate::delta_encode::DeltaError>
+                where W: crate::delta_encode::BitWrite
             {
-                #enc
-                Ok(())
+                #w_enter
+                let __res = (|| {
+                    #enc
+                    Ok(())
+                })();
+                #w_leave
+                __res.map_err(|e: crate::delta_encode::DeltaError| e.in_type(#name_str))
             }
 
             #[inline]
-            fn decode<R>(base: Option<&Self>, r: &mut crate::delta_encode::bitio::Reader<R>) -> ::std::io::Result<Self>
-                where R: std::io::Read
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> ::core::result::Result<Self, crate::delta_encode::DeltaError>
+                where R: crate::delta_encode::BitRead
             {
-                Ok(#dec)
+                #r_enter
+                let __res = (|| {
+                    Ok(#dec)
+                })();
+                #r_leave
+                __res.map_err(|e: crate::delta_encode::DeltaError| e.in_type(#name_str))
             }
+
+            #changed
         }
     }
 }
@@ -119,7 +215,395 @@ fn decode_flags(attrs: &[syn::Attribute]) -> GenFlags {
     flags
 }
 
-fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident, flags: GenFlags, variants: Punctuated<syn::Variant, Comma>) -> (TokenStream, TokenStream) {
+/// Returns the bit count of a `#[name(bits = N)]` attribute, or `default`
+/// if the attribute is given without arguments
+fn bits_attr(attrs: &[syn::Attribute], name: &str, default: TokenStream, min_bits: u64, max_bits: u64) -> Option<TokenStream> {
+    for attr in attrs.into_iter().filter_map(|v| v.interpret_meta()) {
+        match attr {
+            syn::Meta::Word(ref ident) if ident == name => return Some(default),
+            syn::Meta::List(syn::MetaList{ref ident, ref nested, ..}) if ident == name => {
+                let mut bits = default;
+                for meta in nested {
+                    match *meta {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Int(ref val), ..})) if ident == "bits" => {
+                            let val = val.value();
+                            if val < min_bits || val > max_bits {
+                                panic!("Wanted {} bits but `{}` can use between {} and {}", val, name, min_bits, max_bits)
+                            }
+                            let val = val as u8;
+                            bits = quote!(#val);
+                        },
+                        _ => panic!("`{}` only accepts `bits = N`", name),
+                    }
+                }
+                return Some(bits);
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Builds a single field, attributing any errors to `segment`
+fn build_field(
+    ty: syn::Type, flags: GenFlags,
+    encode: &mut Vec<TokenStream>,
+    encode_part: &mut Vec<TokenStream>,
+    decode: &mut Vec<TokenStream>,
+    decode_part: &mut Vec<TokenStream>,
+    de_target: TokenStream,
+    name_self: &TokenStream,
+    name_base: &TokenStream,
+    attrs: &[syn::Attribute],
+    segment: TokenStream,
+) {
+    let mut fencode: Vec<TokenStream> = vec![];
+    let mut fencode_part: Vec<TokenStream> = vec![];
+    let mut fdecode: Vec<TokenStream> = vec![];
+    let mut fdecode_part: Vec<TokenStream> = vec![];
+    let context = context_id(&segment.to_string());
+    build_element(
+        ty, flags,
+        &mut fencode, &mut fencode_part,
+        &mut fdecode, &mut fdecode_part,
+        quote!(),
+        name_self, name_base,
+        attrs,
+        segment,
+    );
+
+    let (w_enter, w_leave, r_enter, r_leave) = model_hooks(attrs);
+    if !fencode.is_empty() {
+        encode.push(quote! {
+            w.enter_context(#context);
+            #w_enter
+            #(#fencode)*
+            #w_leave
+            w.leave_context();
+        });
+    }
+    if !fencode_part.is_empty() {
+        encode_part.push(quote! {
+            w.enter_context(#context);
+            #w_enter
+            #(#fencode_part)*
+            #w_leave
+            w.leave_context();
+        });
+    }
+    decode.push(quote! {
+        #de_target {
+            r.enter_context(#context);
+            #r_enter
+            let __val = #(#fdecode)*;
+            #r_leave
+            r.leave_context();
+            __val
+        }
+    });
+    decode_part.push(quote! {
+        #de_target {
+            r.enter_context(#context);
+            #r_enter
+            let __val = #(#fdecode_part)*;
+            #r_leave
+            r.leave_context();
+            __val
+        }
+    });
+}
+
+/// Builds a value that is part of a field, such as a tuple element,
+/// attributing any errors to `segment` but without entering a context
+/// or model of its own
+fn build_element(
+    ty: syn::Type, flags: GenFlags,
+    encode: &mut Vec<TokenStream>,
+    encode_part: &mut Vec<TokenStream>,
+    decode: &mut Vec<TokenStream>,
+    decode_part: &mut Vec<TokenStream>,
+    de_target: TokenStream,
+    name_self: &TokenStream,
+    name_base: &TokenStream,
+    attrs: &[syn::Attribute],
+    segment: TokenStream,
+) {
+    let mut fencode: Vec<TokenStream> = vec![];
+    let mut fencode_part: Vec<TokenStream> = vec![];
+    let mut fdecode: Vec<TokenStream> = vec![];
+    let mut fdecode_part: Vec<TokenStream> = vec![];
+    build_ty(
+        ty, flags,
+        &mut fencode, &mut fencode_part,
+        &mut fdecode, &mut fdecode_part,
+        quote!(),
+        name_self, name_base,
+        attrs,
+    );
+
+    if !fencode.is_empty() {
+        encode.push(quote! {
+            crate::delta_encode::__within(#segment, || {
+                #(#fencode)*
+                Ok(())
+            })?;
+        });
+    }
+    if !fencode_part.is_empty() {
+        encode_part.push(quote! {
+            crate::delta_encode::__within(#segment, || {
+                #(#fencode_part)*
+                Ok(())
+            })?;
+        });
+    }
+    decode.push(quote! {
+        #de_target crate::delta_encode::__within(#segment, || Ok(#(#fdecode)*))?
+    });
+    decode_part.push(quote! {
+        #de_target crate::delta_encode::__within(#segment, || Ok(#(#fdecode_part)*))?
+    });
+}
+
+/// Returns the calls to enter and leave the `StaticModel` given by
+/// `#[delta_model = "path"]` for the writer and then the reader, or
+/// nothing if there isn't one
+fn model_hooks(attrs: &[syn::Attribute]) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+    for attr in attrs.into_iter().filter_map(|v| v.interpret_meta()) {
+        match attr {
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_model" => {
+                let model: syn::Path = syn::parse_str(&val.value())
+                    .unwrap_or_else(|_| panic!("Invalid delta_model path: {:?}", val.value()));
+                return (
+                    quote!(w.enter_model(&#model);), quote!(w.leave_model();),
+                    quote!(r.enter_model(&#model);), quote!(r.leave_model();),
+                );
+            },
+            _ => {},
+        }
+    }
+    (quote!(), quote!(), quote!(), quote!())
+}
+
+/// Hashes a field's path segment into the id passed to `enter_context`.
+///
+/// Uses FNV-1a so the id is stable between builds.
+fn context_id(segment: &str) -> u32 {
+    segment.bytes().fold(0x811c_9dc5u32, |hash, b| (hash ^ u32::from(b)).wrapping_mul(0x0100_0193))
+}
+
+/// Builds an enum whose variants have no fields. Against the base only a
+/// single bit is sent if the variant is unchanged.
+///
+/// With `#[delta_discriminant]` the variant's discriminant is sent
+/// (offset by the smallest one) instead of its tag so that the values
+/// on the wire match the `#[repr]` values.
+fn build_c_enum(name: &syn::Ident, flags: GenFlags, attrs: &[syn::Attribute], variants: &Punctuated<syn::Variant, Comma>) -> (TokenStream, TokenStream) {
+    let (tags, bits) = match discriminant_attr(attrs) {
+        Some((min, max, bits)) => {
+            if int_attr(attrs, "delta_tag_bits").is_some() {
+                panic!("{} uses both delta_discriminant and delta_tag_bits, use `bits` in delta_discriminant instead", name);
+            }
+            if let Some(variant) = variants.iter().find(|v| int_attr(&v.attrs, "delta_tag").is_some()) {
+                panic!("Variant {} has a delta_tag but {} sends discriminants", variant.ident, name);
+            }
+            let discriminants = variant_discriminants(variants);
+            let min = min.unwrap_or_else(|| *discriminants.iter().min().unwrap());
+            let max = max.unwrap_or_else(|| *discriminants.iter().max().unwrap());
+            if let Some((variant, val)) = variants.iter().zip(&discriminants).find(|&(_, &v)| v < min || v > max) {
+                panic!("The discriminant of {} ({}) is outside of {}..={}", variant.ident, val, min, max)
+            }
+            let range = (max - min) as u64;
+            let needed = (64 - range.leading_zeros()) as u8;
+            let bits = match bits {
+                Some(bits) if bits < needed => panic!("{}..={} doesn't fit in {} bits", min, max, bits),
+                Some(bits) => bits,
+                None => needed,
+            };
+            let tags = discriminants.into_iter().map(|v| (v - min) as u64).collect();
+            (tags, bits)
+        },
+        None => {
+            let tags = variant_tags(variants);
+            let max_tag = tags.iter().cloned().max().unwrap_or(0);
+            (tags, tag_bits(attrs, max_tag))
+        },
+    };
+
+    let mut to_tag = vec![];
+    let mut from_tag = vec![];
+    let mut same = vec![];
+    let mut copy = vec![];
+    for (variant, tag) in variants.iter().zip(tags) {
+        let ident = &variant.ident;
+        to_tag.push(quote!(#name::#ident => #tag));
+        from_tag.push(quote!(#tag => #name::#ident));
+        same.push(quote!((&#name::#ident, &#name::#ident) => true));
+        copy.push(quote!(#name::#ident => #name::#ident));
+    }
+    let encode_tag = quote! {
+        w.write_unsigned(match *self {
+            #(#to_tag,)*
+        }, #bits)?;
+    };
+    let decode_tag = quote! {
+        match r.read_unsigned(#bits)? {
+            #(#from_tag,)*
+            __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
+        }
+    };
+    if flags.contains(GenFlags::ALWAYS) {
+        return (encode_tag, decode_tag);
+    }
+    (quote! {
+        if let Some(base) = base {
+            let __same = match (self, base) {
+                #(#same,)*
+                _ => false,
+            };
+            w.write_bool(!__same)?;
+            if __same {
+                return Ok(());
+            }
+        }
+        #encode_tag
+    }, quote! {{
+        match base {
+            Some(base) if !r.read_bool()? => match *base {
+                #(#copy,)*
+            },
+            _ => #decode_tag,
+        }
+    }})
+}
+
+/// Returns the `min`, `max` and `bits` given to `#[delta_discriminant(..)]`
+/// or `None` if the attribute is missing
+fn discriminant_attr(attrs: &[syn::Attribute]) -> Option<(Option<i128>, Option<i128>, Option<u8>)> {
+    for attr in attrs.into_iter().filter_map(|v| v.interpret_meta()) {
+        match attr {
+            syn::Meta::Word(ref ident) if ident == "delta_discriminant" => return Some((None, None, None)),
+            syn::Meta::List(syn::MetaList{ref ident, ref nested, ..}) if ident == "delta_discriminant" => {
+                let (mut min, mut max, mut bits) = (None, None, None);
+                for meta in nested {
+                    let (key, val) = match *meta {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue{ref ident, ref lit, ..})) => (ident.to_string(), lit),
+                        _ => panic!("`delta_discriminant` only accepts `min`, `max` and `bits`"),
+                    };
+                    let val: i128 = match *val {
+                        syn::Lit::Int(ref val) => val.value() as i128,
+                        syn::Lit::Str(ref val) => val.value().trim().parse()
+                            .unwrap_or_else(|_| panic!("Invalid {} value: {:?}", key, val.value())),
+                        _ => panic!("`{}` must be an integer", key),
+                    };
+                    match key.as_str() {
+                        "min" => min = Some(val),
+                        "max" => max = Some(val),
+                        "bits" if val >= 1 && val <= 64 => bits = Some(val as u8),
+                        "bits" => panic!("Wanted {} bits but the max is 64", val),
+                        _ => panic!("`delta_discriminant` only accepts `min`, `max` and `bits`"),
+                    }
+                }
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        panic!("Empty discriminant range {}..={}", min, max)
+                    }
+                }
+                return Some((min, max, bits));
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Returns the discriminant of each variant, following the language's
+/// rules for variants without one
+fn variant_discriminants(variants: &Punctuated<syn::Variant, Comma>) -> Vec<i128> {
+    let mut next = 0i128;
+    variants.iter()
+        .map(|variant| {
+            let val = match variant.discriminant {
+                Some((_, ref expr)) => int_expr(expr)
+                    .unwrap_or_else(|| panic!("The discriminant of {} must be an integer literal", variant.ident)),
+                None => next,
+            };
+            next = val + 1;
+            val
+        })
+        .collect()
+}
+
+fn int_expr(expr: &syn::Expr) -> Option<i128> {
+    match *expr {
+        syn::Expr::Lit(syn::ExprLit{lit: syn::Lit::Int(ref val), ..}) => Some(val.value() as i128),
+        syn::Expr::Unary(syn::ExprUnary{op: syn::UnOp::Neg(_), ref expr, ..}) => int_expr(expr).map(|v| -v),
+        syn::Expr::Paren(syn::ExprParen{ref expr, ..}) => int_expr(expr),
+        _ => None,
+    }
+}
+
+/// Returns the number of bits tags up to `max_tag` are sent with,
+/// either given by `#[delta_tag_bits = N]` or the fewest that fit
+fn tag_bits(attrs: &[syn::Attribute], max_tag: u64) -> u8 {
+    match int_attr(attrs, "delta_tag_bits") {
+        Some(bits) => {
+            if bits > 64 {
+                panic!("Wanted {} tag bits but the max is 64", bits)
+            }
+            if bits < 64 && max_tag >> bits != 0 {
+                panic!("Tag {} doesn't fit in {} bits", max_tag, bits)
+            }
+            bits as u8
+        },
+        None => (64 - max_tag.leading_zeros()) as u8,
+    }
+}
+
+/// Returns the wire tag of each variant. Variants without a
+/// `#[delta_tag = N]` take the tag after the previous variant's, so
+/// without any they are numbered in declaration order.
+fn variant_tags(variants: &Punctuated<syn::Variant, Comma>) -> Vec<u64> {
+    let mut tags: Vec<u64> = vec![];
+    let mut next = 0u64;
+    for variant in variants {
+        let tag = int_attr(&variant.attrs, "delta_tag").unwrap_or(next);
+        if let Some(pos) = tags.iter().position(|&v| v == tag) {
+            panic!(
+                "Variants {} and {} both use the tag {}",
+                variants[pos].ident, variant.ident, tag
+            );
+        }
+        tags.push(tag);
+        next = tag.checked_add(1)
+            .unwrap_or_else(|| panic!("No tag left for the variant after {}", variant.ident));
+    }
+    tags
+}
+
+/// Returns the value of a `#[name = N]` or `#[name = "N"]` attribute
+fn int_attr(attrs: &[syn::Attribute], name: &str) -> Option<u64> {
+    for attr in attrs.into_iter().filter_map(|v| v.interpret_meta()) {
+        match attr {
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, ref lit, ..}) if ident == name => {
+                return Some(match *lit {
+                    syn::Lit::Int(ref val) => val.value(),
+                    syn::Lit::Str(ref val) => val.value().trim().parse()
+                        .unwrap_or_else(|_| panic!("Invalid {} value: {:?}", name, val.value())),
+                    _ => panic!("`{}` must be an integer", name),
+                });
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+fn field_segment(name: &str) -> TokenStream {
+    quote!(crate::delta_encode::PathSegment::Field(#name))
+}
+
+fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident, flags: GenFlags, attrs: &[syn::Attribute], variants: Punctuated<syn::Variant, Comma>) -> (TokenStream, TokenStream) {
     let mut encode: Vec<TokenStream> = vec![];
     let mut encode_part: Vec<TokenStream> = vec![];
     let mut decode: Vec<TokenStream> = vec![];
@@ -134,15 +618,20 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
         )
     };
 
-    let variant_bits = (variants.len().next_power_of_two() - 1).count_ones() as u8;
+    let tags = variant_tags(&variants);
+    let max_tag = tags.iter().cloned().max().unwrap_or(0);
+    let variant_bits = tag_bits(attrs, max_tag);
 
-    for (idx, variant) in variants.into_iter().enumerate() {
-        let idxu = idx as u64;
+    for (variant, idxu) in variants.into_iter().zip(tags) {
         let encode_variant = quote! {
             w.write_unsigned(#idxu, #variant_bits)?;
         };
 
         let ident = &variant.ident;
+        let variant_segment = {
+            let ident_str = ident.to_string();
+            quote!(crate::delta_encode::PathSegment::Variant(#ident_str))
+        };
         let variant_flags = flags | decode_flags(&variant.attrs);
 
         match variant.fields {
@@ -182,13 +671,14 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                     field_info.push(fname.clone());
                     field_info_base.push(quote!(#fname: ref #name_base_orig));
                     let name_base = quote!(*#name_base_orig);
-                    build_ty(
+                    build_field(
                         field.ty, variant_flags,
                         &mut sencode, &mut sencode_part,
                         &mut sdecode, &mut sdecode_part,
                         quote!(#fname :),
                         &name_self, &name_base,
                         &field.attrs,
+                        field_segment(&fname.to_string()),
                     );
                 }
                 {
@@ -197,7 +687,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                     encode.push(quote!(
                         &#name::#ident{#(ref #field_info),*} => {
                             #encode_variant
-                            #(#sencode)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -210,7 +703,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             &#name::#ident{#(#field_info_base),*},
                         ) => {
                             #encode_variant
-                            #(#sencode_part)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode_part)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -223,7 +719,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             _,
                         ) => {
                             #encode_variant
-                            #(#sencode)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -231,9 +730,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                     let sdecode = sdecode.clone();
                     decode.push(quote!(
                         #idxu => {
-                            #name::#ident {
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident {
                                 #(#sdecode,)*
-                            }
+                            }))?
                         }
                     ));
                 }
@@ -244,9 +743,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             #idxu,
                             &#name::#ident{#(#field_info_base),*},
                         ) => {
-                            #name::#ident {
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident {
                                 #(#sdecode_part,)*
-                            }
+                            }))?
                         }
                     ));
                 }
@@ -257,9 +756,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             #idxu,
                             _,
                         ) => {
-                            #name::#ident {
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident {
                                 #(#sdecode,)*
-                            }
+                            }))?
                         }
                     ));
                 }
@@ -280,13 +779,14 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
 
                     field_info.push(quote!(ref #name_self_orig));
                     field_info_base.push(quote!(ref #name_base_orig));
-                    build_ty(
+                    build_field(
                         field.ty, flags,
                         &mut sencode, &mut sencode_part,
                         &mut sdecode, &mut sdecode_part,
                         quote!(),
                         &name_self, &name_base,
                         &field.attrs,
+                        field_segment(&idx.to_string()),
                     );
                 }
                 {
@@ -295,7 +795,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                     encode.push(quote!(
                         &#name::#ident(#(#field_info),*) => {
                             #encode_variant
-                            #(#sencode)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -308,7 +811,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             &#name::#ident(#(#field_info_base),*),
                         ) => {
                             #encode_variant
-                            #(#sencode_part)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode_part)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -321,7 +827,10 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             _,
                         ) => {
                             #encode_variant
-                            #(#sencode)*
+                            crate::delta_encode::__within(#variant_segment, || {
+                                #(#sencode)*
+                                Ok(())
+                            })?;
                         }
                     ));
                 }
@@ -329,9 +838,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                     let sdecode = sdecode.clone();
                     decode.push(quote!(
                         #idxu => {
-                            #name::#ident (
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident (
                                 #(#sdecode,)*
-                            )
+                            )))?
                         }
                     ));
                 }
@@ -342,9 +851,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             #idxu,
                             &#name::#ident(#(#field_info_base),*),
                         ) => {
-                            #name::#ident (
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident (
                                 #(#sdecode_part,)*
-                            )
+                            )))?
                         }
                     ));
                 }
@@ -355,9 +864,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                             #idxu,
                             _,
                         ) => {
-                            #name::#ident (
+                            crate::delta_encode::__within(#variant_segment, || Ok(#name::#ident (
                                 #(#sdecode,)*
-                            )
+                            )))?
                         }
                     ));
                 }
@@ -368,9 +877,9 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
     if flags.contains(GenFlags::COMPLETE) {
         (quote! {
             if #base_name.map_or(false, |v| *v == *self) {
-                w.write_bool(false);
+                w.write_bool(false)?;
             } else {
-                w.write_bool(true);
+                w.write_bool(true)?;
                 if let Some(#base_name) = #base_name {
                     match (#self_ref, #base_name) {
                         #(#encode_part),*
@@ -388,16 +897,16 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                 (Some(#base_name), true) => {
                     match (r.read_unsigned(#variant_bits)?, #base_name) {
                         #(#decode_part,)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid enum variant")),
+                        (__tag, _) => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 },
                 (None, true) => {
                     match r.read_unsigned(#variant_bits)? {
                         #(#decode,)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid enum variant")),
+                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 },
-                (None, false) => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state")),
+                (None, false) => return Err(crate::delta_encode::DeltaError::missing_baseline()),
             }
         }})
     } else {
@@ -416,13 +925,13 @@ fn build_enum(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident,
                 Some(#base_name) => {
                     match (r.read_unsigned(#variant_bits)?, #base_name) {
                         #(#decode_part,)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid enum variant")),
+                        (__tag, _) => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 },
                 None => {
                     match r.read_unsigned(#variant_bits)? {
                         #(#decode,)*
-                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid enum variant")),
+                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                     }
                 },
             }
@@ -440,13 +949,14 @@ fn build_struct(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Iden
         let fname = field.ident.unwrap();
         let name_self = quote!(#self_name . #fname);
         let name_base = quote!(#base_name . #fname);
-        build_ty(
+        build_field(
             field.ty, flags,
             &mut encode, &mut encode_part,
             &mut decode, &mut decode_part,
             quote!(#fname :),
             &name_self, &name_base,
             &field.attrs,
+            field_segment(&fname.to_string()),
         );
     }
 
@@ -476,7 +986,7 @@ fn build_struct(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Iden
                         #(#decode,)*
                     }
                 },
-                (None, false) => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state")),
+                (None, false) => return Err(crate::delta_encode::DeltaError::missing_baseline()),
             }
         }})
     } else {
@@ -510,22 +1020,23 @@ fn build_tuple(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident
         let index = syn::Index::from(idx);
         let name_self = quote!(#self_name.#index);
         let name_base = quote!(#base_name.#index);
-        build_ty(
+        build_field(
             field.ty, flags,
             &mut encode, &mut encode_part,
             &mut decode, &mut decode_part,
             quote!(),
             &name_self, &name_base,
             &field.attrs,
+            field_segment(&idx.to_string()),
         );
     }
 
     if flags.contains(GenFlags::COMPLETE) {
         (quote! {
             if #base_name.map_or(false, |v| *v == *self) {
-                w.write_bool(false);
+                w.write_bool(false)?;
             } else {
-                w.write_bool(true);
+                w.write_bool(true)?;
                 if let Some(#base_name) = #base_name {
                     #(#encode_part)*
                 } else {
@@ -546,7 +1057,7 @@ fn build_tuple(name: &syn::Ident, self_name: &syn::Ident, base_name: &syn::Ident
                         #(#decode,)*
                     )
                 },
-                (None, false) => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state")),
+                (None, false) => return Err(crate::delta_encode::DeltaError::missing_baseline()),
             }
         }})
     } else {
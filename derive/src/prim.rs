@@ -94,9 +94,18 @@ impl Prim {
 
         let mut bit_size = max_bit_size;
         let mut sub_bits = vec![];
+        let mut code = None;
         flags |= decode_flags(attrs);
         for attr in attrs {
-            match attr.interpret_meta().unwrap() {
+            let meta = attr.interpret_meta().unwrap();
+            if let Some(c) = Code::from_meta(&meta) {
+                if code.is_some() {
+                    panic!("Only one of delta_varint, delta_gamma and delta_rice can be used");
+                }
+                code = Some(c);
+                continue;
+            }
+            match meta {
                 syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_bits" => {
                     let val = val.value();
                     let val: i32 = val.parse().unwrap();
@@ -119,6 +128,14 @@ impl Prim {
             }
         }
 
+        if let Some(code) = code {
+            if bit_size != max_bit_size || !sub_bits.is_empty() {
+                panic!("delta_bits and delta_subbits can't be combined with a variable length code");
+            }
+            code.build(flags, encode, encode_part, decode, decode_part, name_self, name_base, de_target);
+            return;
+        }
+
         let bit_size = bit_size as u8;
 
         macro_rules! gen_prim {
@@ -129,6 +146,7 @@ impl Prim {
                     $(
                     Prim::$key => {
                         if !sub_bits.is_empty() {
+                            let max_sub_bits = *sub_bits.iter().max().unwrap() as u8;
                             let num_states = if flags.contains(GenFlags::ALWAYS) { 0 } else { 1 } + sub_bits.len();
                             let required_bits = (num_states.next_power_of_two() - 1).count_ones() as u8;
                             let mut encode_vals: Vec<TokenStream> = vec![];
@@ -180,14 +198,14 @@ impl Prim {
                                     let __diff_val = #name_self - #name_base;
                                     match __diff_val {
                                         #(#encode_part_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range")),
+                                        _ => return Err(crate::delta_encode::DeltaError::out_of_range(__diff_val as i128, #max_sub_bits)),
                                     }
                                 });
                                 decode_part.push(quote!{
                                     #de_target {
                                         let __diff_val = match r.read_unsigned(#required_bits)? {
                                             #(#decode_part_vals)*
-                                            _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                                            __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                                         };
                                         #name_base + __diff_val
                                     }
@@ -197,39 +215,39 @@ impl Prim {
                                     let __diff_val = #name_self;
                                     match __diff_val {
                                         #(#encode_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range")),
+                                        _ => return Err(crate::delta_encode::DeltaError::out_of_range(__diff_val as i128, #max_sub_bits)),
                                     }
                                 });
                                 decode.push(quote!{
                                     #de_target match r.read_unsigned(#required_bits)? {
                                         #(#decode_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                                     }
                                 });
                             } else {
                                 encode_part.push(quote!{
                                     match #name_self {
                                         #(#encode_part_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range")),
+                                        _ => return Err(crate::delta_encode::DeltaError::out_of_range(#name_self as i128, #max_sub_bits)),
                                     }
                                 });
                                 decode_part.push(quote!{
                                     #de_target match r.read_unsigned(#required_bits)? {
                                         #(#decode_part_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                                     }
                                 });
 
                                 encode.push(quote!{
                                     match #name_self {
                                         #(#encode_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Number out of range")),
+                                        _ => return Err(crate::delta_encode::DeltaError::out_of_range(#name_self as i128, #max_sub_bits)),
                                     }
                                 });
                                 decode.push(quote!{
                                     #de_target match r.read_unsigned(#required_bits)? {
                                         #(#decode_vals)*
-                                        _ => return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid subbit")),
+                                        __tag => return Err(crate::delta_encode::DeltaError::invalid_variant(__tag)),
                                     }
                                 });
                             }
@@ -263,7 +281,7 @@ impl Prim {
                                 #de_target if r.read_bool()? {
                                     r.$dmethod(#bit_size)? as $sty
                                 } else {
-                                    return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Mismatched decode, missing previous state"));
+                                    return Err(crate::delta_encode::DeltaError::missing_baseline());
                                 }
                             });
                             decode_part.push(quote!{
@@ -293,4 +311,107 @@ impl Prim {
             (U64, u64, u64) => (write_unsigned, read_unsigned),
         );
     }
-}
\ No newline at end of file
+}
+
+/// A variable length code picked by `delta_varint`, `delta_gamma` or
+/// `delta_rice`
+enum Code {
+    Varint,
+    Gamma,
+    Rice(u8),
+}
+
+impl Code {
+    fn from_meta(meta: &syn::Meta) -> Option<Code> {
+        match *meta {
+            syn::Meta::Word(ref ident) if ident == "delta_varint" => Some(Code::Varint),
+            syn::Meta::Word(ref ident) if ident == "delta_gamma" => Some(Code::Gamma),
+            syn::Meta::NameValue(syn::MetaNameValue{ref ident, lit: syn::Lit::Str(ref val), ..}) if ident == "delta_rice" => {
+                let k: u8 = val.value().trim().parse()
+                    .unwrap_or_else(|_| panic!("Invalid delta_rice parameter: {:?}", val.value()));
+                if k >= 64 {
+                    panic!("Wanted a rice parameter of {} but the max is 63", k)
+                }
+                Some(Code::Rice(k))
+            },
+            _ => None,
+        }
+    }
+
+    fn write(&self, val: TokenStream) -> TokenStream {
+        match *self {
+            Code::Varint => quote!(crate::delta_encode::write_varint(w, #val)?;),
+            Code::Gamma => quote!(crate::delta_encode::write_gamma(w, #val)?;),
+            Code::Rice(k) => quote!(crate::delta_encode::write_rice(w, #val, #k)?;),
+        }
+    }
+
+    fn read(&self) -> TokenStream {
+        match *self {
+            Code::Varint => quote!(crate::delta_encode::read_varint(r)?),
+            Code::Gamma => quote!(crate::delta_encode::read_gamma(r)?),
+            Code::Rice(k) => quote!(crate::delta_encode::read_rice(r, #k)?),
+        }
+    }
+
+    fn build(
+        &self,
+        flags: GenFlags,
+        encode: &mut Vec<TokenStream>,
+        encode_part: &mut Vec<TokenStream>,
+        decode: &mut Vec<TokenStream>,
+        decode_part: &mut Vec<TokenStream>,
+        name_self: &TokenStream,
+        name_base: &TokenStream,
+        de_target: TokenStream,
+    ) {
+        let write = self.write(quote!(crate::delta_encode::IntCode::to_code(#name_self)));
+        let read = self.read();
+        let (write_part, read_part) = if flags.contains(GenFlags::DIFF) {
+            (
+                self.write(quote!(crate::delta_encode::IntCode::diff_code(#name_self, #name_base))),
+                quote!(crate::delta_encode::IntCode::from_diff_code(#name_base, #read)?),
+            )
+        } else {
+            (write.clone(), quote!(crate::delta_encode::IntCode::from_code(#read)?))
+        };
+
+        if flags.contains(GenFlags::ALWAYS) {
+            encode.push(write);
+            encode_part.push(write_part);
+            decode.push(quote!{
+                #de_target crate::delta_encode::IntCode::from_code(#read)?
+            });
+            decode_part.push(quote!{
+                #de_target #read_part
+            });
+        } else {
+            encode.push(quote!{
+                w.write_bool(true)?;
+                #write
+            });
+            encode_part.push(quote!{
+                if #name_base != #name_self {
+                    w.write_bool(true)?;
+                    #write_part
+                } else {
+                    w.write_bool(false)?
+                }
+            });
+            decode.push(quote!{
+                #de_target if r.read_bool()? {
+                    crate::delta_encode::IntCode::from_code(#read)?
+                } else {
+                    return Err(crate::delta_encode::DeltaError::missing_baseline());
+                }
+            });
+            decode_part.push(quote!{
+                #de_target if r.read_bool()? {
+                    #read_part
+                } else {
+                    #name_base
+                }
+            });
+        }
+    }
+}
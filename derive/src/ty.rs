@@ -13,15 +13,28 @@ pub(crate) fn build_ty(
 ) {
     if decode_flags(&attrs).contains(GenFlags::DEFAULT) {
         decode.push(quote! {
-            #de_target ::std::default::Default::default()
+            #de_target ::core::default::Default::default()
         });
         decode_part.push(quote! {
-            #de_target ::std::default::Default::default()
+            #de_target ::core::default::Default::default()
         });
         return;
     }
     match ty {
         syn::Type::Path(syn::TypePath{path, ..}) => {
+            if build_codec(
+                flags, encode, encode_part, decode, decode_part,
+                &de_target, name_self, name_base, attrs,
+            ) {
+                return;
+            }
+            if has_attr(attrs, "delta_interned") {
+                build_interned(
+                    flags, encode, encode_part, decode, decode_part,
+                    &de_target, name_self, name_base, attrs,
+                );
+                return;
+            }
             if let Some(prim) = path.segments.first() {
                 let prim = prim.value();
                 if let Some(prim) = Prim::from_ident(&prim.ident) {
@@ -59,55 +72,236 @@ pub(crate) fn build_ty(
             encode.push(quote!{
                 crate::delta_encode::DeltaEncodable::encode(&#name_self, None, w)?;
             });
-            encode_part.push(quote!{
-                crate::delta_encode::DeltaEncodable::encode(&#name_self, Some(&#name_base), w)?;
-            });
             decode.push(quote!{
                 #de_target crate::delta_encode::DeltaEncodable::decode(None, r)?
             });
-            decode_part.push(quote!{
-                #de_target crate::delta_encode::DeltaEncodable::decode(Some(&#name_base), r)?
-            });
+            if flags.contains(GenFlags::CHANGED) {
+                encode_part.push(quote!{
+                    crate::delta_encode::DeltaEncodable::encode_changed(&#name_self, &#name_base, w)?;
+                });
+                decode_part.push(quote!{
+                    #de_target crate::delta_encode::DeltaEncodable::decode_changed(&#name_base, r)?
+                });
+            } else {
+                encode_part.push(quote!{
+                    crate::delta_encode::DeltaEncodable::encode(&#name_self, Some(&#name_base), w)?;
+                });
+                decode_part.push(quote!{
+                    #de_target crate::delta_encode::DeltaEncodable::decode(Some(&#name_base), r)?
+                });
+            }
         },
         syn::Type::Array(syn::TypeArray{elem: sub_ty, ..}) => {
+            // Arrays of floats are vectors as far as the codecs are concerned
+            if is_float(&sub_ty) && build_codec(
+                flags, encode, encode_part, decode, decode_part,
+                &de_target, name_self, name_base, attrs,
+            ) {
+                return;
+            }
 
             let mut sencode: Vec<TokenStream> = vec![];
             let mut sencode_part: Vec<TokenStream> = vec![];
             let mut sdecode: Vec<TokenStream> = vec![];
             let mut sdecode_part: Vec<TokenStream> = vec![];
 
+            // Nested in a changed element the array is sent as changed
+            // without a bit of its own
+            let (write_changed, read_changed) = if flags.contains(GenFlags::CHANGED) {
+                (quote!(true), quote!(true))
+            } else {
+                (quote!({
+                    let __changed = __curr != __base;
+                    w.write_bool(__changed)?;
+                    __changed
+                }), quote!(r.read_bool()?))
+            };
+
             let sname_self = quote!(*curr);
             let sname_base = quote!(*base);
             build_ty(
-                *sub_ty, flags,
-                &mut sencode, &mut sencode_part,
-                &mut sdecode, &mut sdecode_part,
+                (*sub_ty).clone(), flags,
+                &mut sencode, &mut vec![],
+                &mut sdecode, &mut vec![],
+                quote!(),
+                &sname_self, &sname_base,
+                attrs,
+            );
+            // Only changed elements are sent against the base so they
+            // don't need their own change bit
+            build_ty(
+                *sub_ty, flags | GenFlags::ALWAYS | GenFlags::CHANGED,
+                &mut vec![], &mut sencode_part,
+                &mut vec![], &mut sdecode_part,
                 quote!(),
                 &sname_self, &sname_base,
                 attrs,
             );
             encode.push(quote!{
-                for curr in (#name_self).iter() {
-                     #(#sencode)*
+                for (idx, curr) in (#name_self).iter().enumerate() {
+                    crate::delta_encode::__within(crate::delta_encode::PathSegment::Index(idx), || {
+                        #(#sencode)*
+                        Ok(())
+                    })?;
                 }
             });
             encode_part.push(quote!{
-                for (curr, base) in (#name_self).iter().zip((#name_base).iter()) {
-                    #(#sencode_part)*
+                {
+                    let __curr = &(#name_self);
+                    let __base = &(#name_base);
+                    if #write_changed {
+                        crate::delta_encode::write_changes(w, __curr.len(), |idx| __curr[idx] != __base[idx], |w, idx| {
+                            let curr = &__curr[idx];
+                            let base = &__base[idx];
+                            crate::delta_encode::__within(crate::delta_encode::PathSegment::Index(idx), || {
+                                #(#sencode_part)*
+                                Ok(())
+                            })
+                        })?;
+                    }
                 }
             });
             decode.push(quote!{
-                #de_target crate::delta_encode::CreateArray::create::<_, ::std::io::Error>(|_offset| {
-                    Ok(#(#sdecode)*)
+                #de_target crate::delta_encode::CreateArray::create::<_, crate::delta_encode::DeltaError>(|offset| {
+                    crate::delta_encode::__within(crate::delta_encode::PathSegment::Index(offset), || Ok(#(#sdecode)*))
                 })?
             });
             decode_part.push(quote!{
-                #de_target crate::delta_encode::CreateArray::create::<_, ::std::io::Error>(|offset| {
-                    let base = &(#name_base)[offset];
-                    Ok(#(#sdecode_part)*)
-                })?
+                #de_target {
+                    let __base = &(#name_base);
+                    if #read_changed {
+                        let mut __changes = crate::delta_encode::ChangeReader::new(r, __base.len())?;
+                        crate::delta_encode::CreateArray::create::<_, crate::delta_encode::DeltaError>(|offset| {
+                            let base = &__base[offset];
+                            if __changes.changed(r, offset)? {
+                                crate::delta_encode::__within(crate::delta_encode::PathSegment::Index(offset), || Ok(#(#sdecode_part)*))
+                            } else {
+                                Ok(::core::clone::Clone::clone(base))
+                            }
+                        })?
+                    } else {
+                        ::core::clone::Clone::clone(__base)
+                    }
+                }
+            });
+        },
+        syn::Type::Tuple(syn::TypeTuple{elems, ..}) => {
+            let mut sdecode: Vec<TokenStream> = vec![];
+            let mut sdecode_part: Vec<TokenStream> = vec![];
+
+            for (idx, sub_ty) in elems.into_iter().enumerate() {
+                let field = syn::Index::from(idx);
+                build_element(
+                    sub_ty, flags,
+                    encode, encode_part,
+                    &mut sdecode, &mut sdecode_part,
+                    quote!(),
+                    &quote!((#name_self).#field), &quote!((#name_base).#field),
+                    attrs,
+                    quote!(crate::delta_encode::PathSegment::Index(#idx)),
+                );
+            }
+            decode.push(quote!{
+                #de_target (#(#sdecode,)*)
+            });
+            decode_part.push(quote!{
+                #de_target (#(#sdecode_part,)*)
             });
         },
         ty => unimplemented!("Other type: {:?}", ty),
     }
-}
\ No newline at end of file
+}
+
+fn is_float(ty: &syn::Type) -> bool {
+    match *ty {
+        syn::Type::Path(syn::TypePath{ref path, ..}) => path.is_ident("f32") || path.is_ident("f64"),
+        _ => false,
+    }
+}
+
+/// Attributes that send a field through a pair of `encode_*`/`decode_*`
+/// functions taking a bit count between the given bounds
+const CODECS: &[(&str, &str, &str, &str, u64, u64)] = &[
+    ("delta_quat", "encode_quat", "decode_quat", "DEFAULT_QUAT_BITS", 2, 30),
+    ("delta_unit_vector", "encode_direction", "decode_direction", "DEFAULT_DIRECTION_BITS", 2, 30),
+];
+
+fn build_codec(
+    flags: GenFlags,
+    encode: &mut Vec<TokenStream>,
+    encode_part: &mut Vec<TokenStream>,
+    decode: &mut Vec<TokenStream>,
+    decode_part: &mut Vec<TokenStream>,
+    de_target: &TokenStream,
+    name_self: &TokenStream,
+    name_base: &TokenStream,
+    attrs: &[syn::Attribute]
+) -> bool {
+    for &(attr, enc, dec, default, min_bits, max_bits) in CODECS {
+        let default = syn::Ident::new(default, Span::call_site());
+        let bits = match bits_attr(attrs, attr, quote!(crate::delta_encode::#default), min_bits, max_bits) {
+            Some(bits) => bits,
+            None => continue,
+        };
+        let (enc, dec) = (syn::Ident::new(enc, Span::call_site()), syn::Ident::new(dec, Span::call_site()));
+        let base = if (flags | decode_flags(attrs)).contains(GenFlags::ALWAYS) {
+            quote!(None)
+        } else {
+            quote!(Some(&#name_base))
+        };
+        encode.push(quote!{
+            crate::delta_encode::#enc(&#name_self, None, #bits, w)?;
+        });
+        encode_part.push(quote!{
+            crate::delta_encode::#enc(&#name_self, #base, #bits, w)?;
+        });
+        decode.push(quote!{
+            #de_target crate::delta_encode::#dec(None, #bits, r)?
+        });
+        decode_part.push(quote!{
+            #de_target crate::delta_encode::#dec(#base, #bits, r)?
+        });
+        return true;
+    }
+    false
+}
+
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter()
+        .filter_map(|v| v.interpret_meta())
+        .any(|v| match v {
+            syn::Meta::Word(ref ident) => ident == name,
+            _ => false,
+        })
+}
+
+/// Sends a string type through `encode_interned`, decoding via `String`
+fn build_interned(
+    flags: GenFlags,
+    encode: &mut Vec<TokenStream>,
+    encode_part: &mut Vec<TokenStream>,
+    decode: &mut Vec<TokenStream>,
+    decode_part: &mut Vec<TokenStream>,
+    de_target: &TokenStream,
+    name_self: &TokenStream,
+    name_base: &TokenStream,
+    attrs: &[syn::Attribute]
+) {
+    let base = if (flags | decode_flags(attrs)).contains(GenFlags::ALWAYS) {
+        quote!(None)
+    } else {
+        quote!(Some(::core::convert::AsRef::<str>::as_ref(&#name_base)))
+    };
+    encode.push(quote!{
+        crate::delta_encode::encode_interned(::core::convert::AsRef::<str>::as_ref(&#name_self), None, w)?;
+    });
+    encode_part.push(quote!{
+        crate::delta_encode::encode_interned(::core::convert::AsRef::<str>::as_ref(&#name_self), #base, w)?;
+    });
+    decode.push(quote!{
+        #de_target ::core::convert::From::from(crate::delta_encode::decode_interned(None, r)?)
+    });
+    decode_part.push(quote!{
+        #de_target ::core::convert::From::from(crate::delta_encode::decode_interned(#base, r)?)
+    });
+}
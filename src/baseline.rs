@@ -0,0 +1,170 @@
+use super::*;
+use alloc::collections::VecDeque;
+
+/// Returns whether sequence `a` comes after `b`, allowing for wrapping
+#[inline]
+fn is_newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+/// Sender side history of snapshots.
+///
+/// Every encoded value is stored under a sequence number. Once the peer
+/// acknowledges a sequence number it becomes the baseline that later
+/// values are encoded against, snapshots older than it are dropped.
+pub struct BaselineStore<T> {
+    snapshots: VecDeque<(u32, T)>,
+    capacity: usize,
+    next_sequence: u32,
+    acked: Option<u32>,
+}
+
+impl <T> BaselineStore<T>
+    where T: DeltaEncodable + Clone
+{
+    /// Creates a store that keeps at most `capacity` snapshots.
+    ///
+    /// When full the oldest unacknowledged snapshot is evicted first
+    pub fn new(capacity: usize) -> BaselineStore<T> {
+        assert!(capacity > 0, "Capacity must be at least one");
+        BaselineStore {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            next_sequence: 0,
+            acked: None,
+        }
+    }
+
+    /// Records that the peer received the value with the given
+    /// sequence number.
+    ///
+    /// Acks for snapshots that have been evicted or that are older than
+    /// the current baseline are ignored.
+    pub fn ack(&mut self, sequence: u32) {
+        if self.acked.is_some_and(|v| !is_newer(sequence, v)) {
+            return;
+        }
+        if self.get(sequence).is_none() {
+            return;
+        }
+        self.acked = Some(sequence);
+        while self.snapshots.front().is_some_and(|v| v.0 != sequence) {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// The newest acknowledged snapshot, if any
+    pub fn baseline(&self) -> Option<(u32, &T)> {
+        let acked = self.acked?;
+        self.get(acked).map(|v| (acked, v))
+    }
+
+    /// Returns the snapshot stored under the sequence number
+    pub fn get(&self, sequence: u32) -> Option<&T> {
+        self.snapshots.iter()
+            .find(|v| v.0 == sequence)
+            .map(|v| &v.1)
+    }
+
+    /// Encodes `value` against the current baseline and stores it,
+    /// returning the sequence number it was sent with.
+    pub fn encode<W>(&mut self, value: &T, w: &mut W) -> Result<u32, DeltaError>
+        where W: BitWrite
+    {
+        let sequence = self.next_sequence;
+        w.write_unsigned(u64::from(sequence), 32)?;
+        let base = self.baseline();
+        if let Some((base_sequence, _)) = base {
+            w.write_bool(true)?;
+            w.write_len(sequence.wrapping_sub(base_sequence) as usize)?;
+        } else {
+            w.write_bool(false)?;
+        }
+        value.encode(base.map(|v| v.1), w)?;
+
+        self.next_sequence = sequence.wrapping_add(1);
+        if self.snapshots.len() == self.capacity {
+            // Keep the acked baseline around, it's still needed
+            let idx = if self.acked.is_some() && self.snapshots.len() > 1 { 1 } else { 0 };
+            if idx == 0 {
+                self.acked = None;
+            }
+            self.snapshots.remove(idx);
+        }
+        self.snapshots.push_back((sequence, value.clone()));
+        Ok(sequence)
+    }
+}
+
+/// Receiver side history of snapshots.
+///
+/// Decodes values written by `BaselineStore::encode` looking up the
+/// baseline they were encoded against. The sequence numbers returned
+/// should be acknowledged back to the sender.
+pub struct BaselineReceiver<T> {
+    snapshots: VecDeque<(u32, T)>,
+    capacity: usize,
+}
+
+impl <T> BaselineReceiver<T>
+    where T: DeltaEncodable
+{
+    /// Creates a store that keeps the `capacity` newest snapshots
+    pub fn new(capacity: usize) -> BaselineReceiver<T> {
+        assert!(capacity > 0, "Capacity must be at least one");
+        BaselineReceiver {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the snapshot stored under the sequence number
+    pub fn get(&self, sequence: u32) -> Option<&T> {
+        self.snapshots.iter()
+            .find(|v| v.0 == sequence)
+            .map(|v| &v.1)
+    }
+
+    /// The most recently sent snapshot that was received
+    pub fn latest(&self) -> Option<(u32, &T)> {
+        self.snapshots.back().map(|v| (v.0, &v.1))
+    }
+
+    /// Decodes a value and stores it as a possible future baseline.
+    ///
+    /// Fails with `DeltaError::MissingBaseline` if the baseline it was
+    /// encoded against has already been evicted.
+    pub fn decode<R>(&mut self, r: &mut R) -> Result<(u32, &T), DeltaError>
+        where R: BitRead
+    {
+        let sequence = r.read_unsigned(32)? as u32;
+        let base = if r.read_bool()? {
+            let base_sequence = sequence.wrapping_sub(r.read_len()? as u32);
+            Some(self.get(base_sequence).ok_or_else(DeltaError::missing_baseline)?)
+        } else {
+            None
+        };
+        let value = T::decode(base, r)?;
+
+        // Keep the snapshots ordered so the oldest is evicted first
+        let idx = self.snapshots.iter()
+            .rposition(|v| !is_newer(v.0, sequence))
+            .map_or(0, |v| v + 1);
+        if idx > 0 && self.snapshots[idx - 1].0 == sequence {
+            self.snapshots[idx - 1].1 = value;
+            return Ok((sequence, &self.snapshots[idx - 1].1));
+        }
+        self.snapshots.insert(idx, (sequence, value));
+        let mut idx = idx;
+        if self.snapshots.len() > self.capacity {
+            // Evict the oldest snapshot other than the one just decoded
+            if idx == 0 {
+                self.snapshots.remove(1);
+            } else {
+                self.snapshots.pop_front();
+                idx -= 1;
+            }
+        }
+        Ok((sequence, &self.snapshots[idx].1))
+    }
+}
@@ -0,0 +1,390 @@
+use super::*;
+use core::convert::TryFrom;
+
+/// A sink that encoded values are written to bit by bit.
+///
+/// Only the primitive methods need implementing, the length and string
+/// helpers are built on top of them so every implementation produces the
+/// same layout for them.
+pub trait BitWrite {
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError>;
+
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError>;
+
+    #[inline]
+    fn write_signed(&mut self, val: i64, bits: u8) -> Result<(), DeltaError> {
+        self.write_unsigned(val as u64 & mask(bits), bits)
+    }
+
+    #[inline]
+    fn write_f32(&mut self, val: f32) -> Result<(), DeltaError> {
+        self.write_unsigned(u64::from(val.to_bits()), 32)
+    }
+
+    #[inline]
+    fn write_f64(&mut self, val: f64) -> Result<(), DeltaError> {
+        self.write_unsigned(val.to_bits(), 64)
+    }
+
+    /// Called before a field is written with an id identifying it
+    /// within its parent, so that writers that model their input can keep
+    /// separate statistics per field. Does nothing by default.
+    #[inline]
+    fn enter_context(&mut self, _id: u32) {}
+
+    /// Called once the field passed to `enter_context` has been written
+    #[inline]
+    fn leave_context(&mut self) {}
+
+    /// Called before a value marked with `#[delta_model]` is written so
+    /// that writers that model their input can use `model`'s fixed
+    /// probabilities for it. Does nothing by default.
+    #[inline]
+    fn enter_model(&mut self, _model: &'static StaticModel) {}
+
+    /// Called once the value passed to `enter_model` has been written
+    #[inline]
+    fn leave_model(&mut self) {}
+
+    /// Returns the id of `val` in the `StringTable` shared with the
+    /// reader, used by `#[delta_interned]` fields. Writers have no table
+    /// by default, see `InternWriter`.
+    #[inline]
+    fn string_id(&self, _val: &str) -> Option<u32> {
+        None
+    }
+
+    /// Writes a length with `write_varint`
+    #[inline]
+    fn write_len(&mut self, len: usize) -> Result<(), DeltaError> {
+        write_varint(self, len as u64)
+    }
+
+    /// Writes a string, only sending a single bit if it matches `base`.
+    ///
+    /// When there is a base the string is either sent in full or as the
+    /// lengths of the prefix and suffix it shares with the base plus the
+    /// bytes in between, whichever is smaller.
+    #[inline]
+    fn write_str(&mut self, val: &str, base: Option<&str>) -> Result<(), DeltaError> {
+        if base == Some(val) {
+            return self.write_bool(false);
+        }
+        self.write_bool(true)?;
+        write_changed_str(self, val, base)
+    }
+}
+
+/// Writes the part of `BitWrite::write_str` after the changed bit
+pub(crate) fn write_changed_str<W>(w: &mut W, val: &str, base: Option<&str>) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    let val = val.as_bytes();
+    if let Some(base) = base.map(str::as_bytes) {
+        let prefix = val.iter()
+            .zip(base)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = val[prefix ..].iter().rev()
+            .zip(base[prefix ..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let middle = &val[prefix .. val.len() - suffix];
+        let full_bits = len_bits(val.len()) + val.len() * 8;
+        let splice_bits = len_bits(prefix) + len_bits(suffix) + len_bits(middle.len()) + middle.len() * 8;
+        if splice_bits < full_bits {
+            w.write_bool(true)?;
+            w.write_len(prefix)?;
+            w.write_len(suffix)?;
+            return write_bytes(w, middle);
+        }
+        w.write_bool(false)?;
+    }
+    write_bytes(w, val)
+}
+
+pub(crate) fn write_bytes<W>(w: &mut W, val: &[u8]) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    w.write_len(val.len())?;
+    for &b in val {
+        w.write_unsigned(u64::from(b), 8)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn read_bytes<R>(r: &mut R, buf: &mut Vec<u8>) -> Result<(), DeltaError>
+    where R: BitRead + ?Sized
+{
+    let len = r.read_len()?;
+    buf.reserve(len);
+    for _ in 0 .. len {
+        buf.push(r.read_unsigned(8)? as u8);
+    }
+    Ok(())
+}
+
+/// Returns the number of bits `BitWrite::write_len` uses for `len`
+#[inline]
+pub(crate) fn len_bits(len: usize) -> usize {
+    let used = (usize::BITS - len.leading_zeros()) as usize;
+    8 * core::cmp::max(1, used.div_ceil(7))
+}
+
+/// A source that encoded values are read from bit by bit.
+///
+/// The counterpart to `BitWrite`.
+pub trait BitRead {
+    fn read_bool(&mut self) -> Result<bool, DeltaError>;
+
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError>;
+
+    #[inline]
+    fn read_signed(&mut self, bits: u8) -> Result<i64, DeltaError> {
+        let val = self.read_unsigned(bits)?;
+        if bits == 0 {
+            return Ok(0);
+        }
+        let shift = 64 - u32::from(bits);
+        Ok(((val << shift) as i64) >> shift)
+    }
+
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32, DeltaError> {
+        Ok(f32::from_bits(self.read_unsigned(32)? as u32))
+    }
+
+    #[inline]
+    fn read_f64(&mut self) -> Result<f64, DeltaError> {
+        Ok(f64::from_bits(self.read_unsigned(64)?))
+    }
+
+    /// The counterpart to `BitWrite::enter_context`, called with the same
+    /// ids in the same order. Does nothing by default.
+    #[inline]
+    fn enter_context(&mut self, _id: u32) {}
+
+    /// The counterpart to `BitWrite::leave_context`
+    #[inline]
+    fn leave_context(&mut self) {}
+
+    /// The counterpart to `BitWrite::enter_model`
+    #[inline]
+    fn enter_model(&mut self, _model: &'static StaticModel) {}
+
+    /// The counterpart to `BitWrite::leave_model`
+    #[inline]
+    fn leave_model(&mut self) {}
+
+    /// Returns the string with the given id in the `StringTable` shared
+    /// with the writer. Readers have no table by default, see
+    /// `InternReader`.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn interned_string(&self, _id: u32) -> Option<Arc<str>> {
+        None
+    }
+
+    #[inline]
+    fn read_len(&mut self) -> Result<usize, DeltaError> {
+        let len = read_varint(self)?;
+        usize::try_from(len)
+            .map_err(|_| DeltaError::invalid_data("length too large"))
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn read_string(&mut self, base: Option<&str>) -> Result<String, DeltaError> {
+        if !self.read_bool()? {
+            return base
+                .map(|v| v.to_owned())
+                .ok_or_else(DeltaError::missing_baseline);
+        }
+        read_changed_str(self, base)
+    }
+}
+
+/// Reads the part of `BitRead::read_string` after the changed bit
+#[cfg(feature = "alloc")]
+pub(crate) fn read_changed_str<R>(r: &mut R, base: Option<&str>) -> Result<String, DeltaError>
+    where R: BitRead + ?Sized
+{
+    let mut buf = Vec::new();
+    match base.map(str::as_bytes) {
+        Some(base) if r.read_bool()? => {
+            let prefix = r.read_len()?;
+            let suffix = r.read_len()?;
+            if prefix.checked_add(suffix).is_none_or(|v| v > base.len()) {
+                return Err(DeltaError::invalid_data("string splice doesn't match the base"));
+            }
+            buf.extend_from_slice(&base[.. prefix]);
+            read_bytes(r, &mut buf)?;
+            buf.extend_from_slice(&base[base.len() - suffix ..]);
+        },
+        _ => read_bytes(r, &mut buf)?,
+    }
+    String::from_utf8(buf)
+        .map_err(|_| DeltaError::invalid_data("invalid utf-8"))
+}
+
+/// A `BitWrite` that discards everything written to it and only counts
+/// the number of bits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BitCounter {
+    bits: usize,
+}
+
+impl BitCounter {
+    pub fn new() -> BitCounter {
+        BitCounter::default()
+    }
+
+    /// The number of bits written so far
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+}
+
+impl BitWrite for BitCounter {
+    #[inline]
+    fn write_bool(&mut self, _val: bool) -> Result<(), DeltaError> {
+        self.bits += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn write_unsigned(&mut self, _val: u64, bits: u8) -> Result<(), DeltaError> {
+        self.bits += usize::from(bits);
+        Ok(())
+    }
+}
+
+#[inline]
+pub(crate) fn mask(bits: u8) -> u64 {
+    if bits >= 64 {
+        !0
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+impl <B> BitWrite for &mut B
+    where B: BitWrite + ?Sized
+{
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> { (**self).write_bool(val) }
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> { (**self).write_unsigned(val, bits) }
+    #[inline]
+    fn write_signed(&mut self, val: i64, bits: u8) -> Result<(), DeltaError> { (**self).write_signed(val, bits) }
+    #[inline]
+    fn write_f32(&mut self, val: f32) -> Result<(), DeltaError> { (**self).write_f32(val) }
+    #[inline]
+    fn write_f64(&mut self, val: f64) -> Result<(), DeltaError> { (**self).write_f64(val) }
+    #[inline]
+    fn enter_context(&mut self, id: u32) { (**self).enter_context(id) }
+    #[inline]
+    fn leave_context(&mut self) { (**self).leave_context() }
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) { (**self).enter_model(model) }
+    #[inline]
+    fn leave_model(&mut self) { (**self).leave_model() }
+    #[inline]
+    fn string_id(&self, val: &str) -> Option<u32> { (**self).string_id(val) }
+    #[inline]
+    fn write_len(&mut self, len: usize) -> Result<(), DeltaError> { (**self).write_len(len) }
+    #[inline]
+    fn write_str(&mut self, val: &str, base: Option<&str>) -> Result<(), DeltaError> { (**self).write_str(val, base) }
+}
+
+impl <B> BitRead for &mut B
+    where B: BitRead + ?Sized
+{
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, DeltaError> { (**self).read_bool() }
+    #[inline]
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> { (**self).read_unsigned(bits) }
+    #[inline]
+    fn read_signed(&mut self, bits: u8) -> Result<i64, DeltaError> { (**self).read_signed(bits) }
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32, DeltaError> { (**self).read_f32() }
+    #[inline]
+    fn read_f64(&mut self) -> Result<f64, DeltaError> { (**self).read_f64() }
+    #[inline]
+    fn enter_context(&mut self, id: u32) { (**self).enter_context(id) }
+    #[inline]
+    fn leave_context(&mut self) { (**self).leave_context() }
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) { (**self).enter_model(model) }
+    #[inline]
+    fn leave_model(&mut self) { (**self).leave_model() }
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn interned_string(&self, id: u32) -> Option<Arc<str>> { (**self).interned_string(id) }
+    #[inline]
+    fn read_len(&mut self) -> Result<usize, DeltaError> { (**self).read_len() }
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn read_string(&mut self, base: Option<&str>) -> Result<String, DeltaError> { (**self).read_string(base) }
+}
+
+#[cfg(feature = "std")]
+impl <W> BitWrite for bitio::Writer<W>
+    where W: Write
+{
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> {
+        Ok(bitio::Writer::write_bool(self, val)?)
+    }
+
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> {
+        Ok(bitio::Writer::write_unsigned(self, val, bits)?)
+    }
+
+    #[inline]
+    fn write_signed(&mut self, val: i64, bits: u8) -> Result<(), DeltaError> {
+        Ok(bitio::Writer::write_signed(self, val, bits)?)
+    }
+
+    #[inline]
+    fn write_f32(&mut self, val: f32) -> Result<(), DeltaError> {
+        Ok(bitio::Writer::write_f32(self, val)?)
+    }
+
+    #[inline]
+    fn write_f64(&mut self, val: f64) -> Result<(), DeltaError> {
+        Ok(bitio::Writer::write_f64(self, val)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl <R> BitRead for bitio::Reader<R>
+    where R: Read
+{
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, DeltaError> {
+        Ok(bitio::Reader::read_bool(self)?)
+    }
+
+    #[inline]
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> {
+        Ok(bitio::Reader::read_unsigned(self, bits)?)
+    }
+
+    #[inline]
+    fn read_signed(&mut self, bits: u8) -> Result<i64, DeltaError> {
+        Ok(bitio::Reader::read_signed(self, bits)?)
+    }
+
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32, DeltaError> {
+        Ok(bitio::Reader::read_f32(self)?)
+    }
+
+    #[inline]
+    fn read_f64(&mut self) -> Result<f64, DeltaError> {
+        Ok(bitio::Reader::read_f64(self)?)
+    }
+}
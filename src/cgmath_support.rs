@@ -1,24 +1,114 @@
 use super::*;
+use cgmath::{Angle, BaseFloat};
+use cgmath::num_traits::NumCast;
 
-impl DeltaEncodable for cgmath::Vector3<f32> {
+// Against a base a single bit is sent when the value is unchanged,
+// otherwise each component is sent with its own change bit.
+macro_rules! impl_cgmath {
+    ($(impl <$param:ident: $bound:ident> $name:ident { $($field:tt: $fty:ty),* })*) => {
+    $(
+        impl <$param> DeltaEncodable for cgmath::$name<$param>
+            where $param: $bound + DeltaEncodable
+        {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if let Some(base) = base {
+                    if base == self {
+                        return w.write_bool(false);
+                    }
+                    w.write_bool(true)?;
+                }
+                $(
+                    <$fty>::encode(&self.$field, base.map(|v| &v.$field), w)
+                        .map_err(|e| e.within(PathSegment::Field(stringify!($field))))?;
+                )*
+                Ok(())
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if let Some(base) = base {
+                    if !r.read_bool()? {
+                        return Ok(*base);
+                    }
+                }
+                Ok(cgmath::$name {
+                    $(
+                        $field: <$fty>::decode(base.map(|v| &v.$field), r)
+                            .map_err(|e| e.within(PathSegment::Field(stringify!($field))))?,
+                    )*
+                })
+            }
+        }
+    )*
+    };
+}
+
+impl_cgmath! {
+    impl <S: BaseFloat> Vector2 { x: S, y: S }
+    impl <S: BaseFloat> Vector3 { x: S, y: S, z: S }
+    impl <S: BaseFloat> Vector4 { x: S, y: S, z: S, w: S }
+    impl <S: BaseFloat> Point2 { x: S, y: S }
+    impl <S: BaseFloat> Point3 { x: S, y: S, z: S }
+    impl <S: BaseFloat> Matrix3 { x: cgmath::Vector3<S>, y: cgmath::Vector3<S>, z: cgmath::Vector3<S> }
+    impl <S: BaseFloat> Matrix4 {
+        x: cgmath::Vector4<S>, y: cgmath::Vector4<S>, z: cgmath::Vector4<S>, w: cgmath::Vector4<S>
+    }
+    impl <S: BaseFloat> Deg { 0: S }
+    impl <S: BaseFloat> Rad { 0: S }
+    impl <A: Angle> Euler { x: A, y: A, z: A }
+}
+
+impl <S> QuatComponents for cgmath::Quaternion<S>
+    where S: BaseFloat
+{
+    #[inline]
+    fn to_xyzw(&self) -> [f64; 4] {
+        [self.v.x, self.v.y, self.v.z, self.s].map(|v| v.to_f64().unwrap_or(0.0))
+    }
+
+    #[inline]
+    fn from_xyzw(xyzw: [f64; 4]) -> Self {
+        let [x, y, z, w] = xyzw.map(|v| <S as NumCast>::from(v).unwrap_or_else(S::zero));
+        cgmath::Quaternion::new(w, x, y, z)
+    }
+}
+
+/// Sent as a normalized rotation using `encode_quat` with
+/// `DEFAULT_QUAT_BITS` bits per component
+impl <S> DeltaEncodable for cgmath::Quaternion<S>
+    where S: BaseFloat
+{
     #[inline]
-    fn encode<W>(&self, _base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
-        w.write_f32(self.x)?;
-        w.write_f32(self.y)?;
-        w.write_f32(self.z)?;
-        Ok(())
+        encode_quat(self, base, DEFAULT_QUAT_BITS, w)
     }
 
     #[inline]
-    fn decode<R>(_base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        Ok(cgmath::Vector3::new(
-            r.read_f32()?,
-            r.read_f32()?,
-            r.read_f32()?,
-        ))
+        decode_quat(base, DEFAULT_QUAT_BITS, r)
+    }
+}
+
+impl <S> Direction3 for cgmath::Vector3<S>
+    where S: BaseFloat
+{
+    #[inline]
+    fn to_xyz(&self) -> [f64; 3] {
+        [self.x, self.y, self.z].map(|v| v.to_f64().unwrap_or(0.0))
+    }
+
+    #[inline]
+    fn from_xyz(xyz: [f64; 3]) -> Self {
+        let [x, y, z] = xyz.map(|v| <S as NumCast>::from(v).unwrap_or_else(S::zero));
+        cgmath::Vector3::new(x, y, z)
     }
-}
\ No newline at end of file
+}
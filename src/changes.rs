@@ -0,0 +1,129 @@
+use super::*;
+
+/// Writes which of `len` elements changed followed by the elements
+/// themselves.
+///
+/// The changed elements are marked either with a bit per element or as
+/// a list of gaps between changed indices, whichever is smaller. `encode`
+/// is called for each changed index in order and is interleaved with the
+/// markers so that `read_changes` doesn't need to buffer them.
+pub fn write_changes<W, C, E>(w: &mut W, len: usize, changed: C, mut encode: E) -> Result<(), DeltaError>
+    where W: BitWrite,
+          C: Fn(usize) -> bool,
+          E: FnMut(&mut W, usize) -> Result<(), DeltaError>
+{
+    let mut count = 0;
+    let mut list_bits = 0;
+    let mut expected = 0;
+    for idx in (0 .. len).filter(|&idx| changed(idx)) {
+        count += 1;
+        list_bits += len_bits(idx - expected);
+        expected = idx + 1;
+    }
+    list_bits += len_bits(count);
+
+    if list_bits < len {
+        w.write_bool(true)?;
+        w.write_len(count)?;
+        let mut expected = 0;
+        for idx in (0 .. len).filter(|&idx| changed(idx)) {
+            w.write_len(idx - expected)?;
+            encode(w, idx)?;
+            expected = idx + 1;
+        }
+    } else {
+        w.write_bool(false)?;
+        for idx in 0 .. len {
+            let changed = changed(idx);
+            w.write_bool(changed)?;
+            if changed {
+                encode(w, idx)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the markers written by `write_changes` one index at a time.
+///
+/// `changed` must be called for every index in order, decoding the
+/// element whenever it returns `true`.
+pub struct ChangeReader {
+    len: usize,
+    list: bool,
+    remaining: usize,
+    next: Option<usize>,
+    /// The index after the last change returned. The gap to the next
+    /// change follows that element so it is only read on the next call.
+    pending: Option<usize>,
+}
+
+impl ChangeReader {
+    pub fn new<R>(r: &mut R, len: usize) -> Result<ChangeReader, DeltaError>
+        where R: BitRead
+    {
+        let mut reader = ChangeReader {
+            len,
+            list: r.read_bool()?,
+            remaining: 0,
+            next: None,
+            pending: None,
+        };
+        if reader.list {
+            reader.remaining = r.read_len()?;
+            if reader.remaining > len {
+                return Err(DeltaError::invalid_data("too many changed elements"));
+            }
+            reader.read_next(r, 0)?;
+        }
+        Ok(reader)
+    }
+
+    fn read_next<R>(&mut self, r: &mut R, expected: usize) -> Result<(), DeltaError>
+        where R: BitRead
+    {
+        if self.remaining == 0 {
+            self.next = None;
+            return Ok(());
+        }
+        self.remaining -= 1;
+        let next = r.read_len()?.checked_add(expected)
+            .filter(|&v| v < self.len)
+            .ok_or_else(|| DeltaError::invalid_data("changed index out of range"))?;
+        self.next = Some(next);
+        Ok(())
+    }
+
+    /// Returns whether the element at `idx` changed
+    pub fn changed<R>(&mut self, r: &mut R, idx: usize) -> Result<bool, DeltaError>
+        where R: BitRead
+    {
+        if !self.list {
+            return r.read_bool();
+        }
+        if let Some(expected) = self.pending.take() {
+            self.read_next(r, expected)?;
+        }
+        if self.next != Some(idx) {
+            return Ok(false);
+        }
+        self.pending = Some(idx + 1);
+        Ok(true)
+    }
+}
+
+/// Reads the output of `write_changes`.
+///
+/// `decode` is called for every index in order along with whether the
+/// element changed.
+pub fn read_changes<R, D>(r: &mut R, len: usize, mut decode: D) -> Result<(), DeltaError>
+    where R: BitRead,
+          D: FnMut(&mut R, usize, bool) -> Result<(), DeltaError>
+{
+    let mut changes = ChangeReader::new(r, len)?;
+    for idx in 0 .. len {
+        let changed = changes.changed(r, idx)?;
+        decode(r, idx, changed)?;
+    }
+    Ok(())
+}
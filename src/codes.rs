@@ -0,0 +1,268 @@
+use super::*;
+use core::convert::TryFrom;
+
+/// Above this quotient `write_rice` stops sending it in unary and
+/// sends the rest with `write_gamma` instead.
+const RICE_ESCAPE: u64 = 32;
+
+/// Maps signed integers onto unsigned ones so that small magnitudes of
+/// either sign become small codes, `0, -1, 1, -2, ...` to `0, 1, 2, 3, ...`
+#[inline]
+pub fn zigzag(val: i64) -> u64 {
+    ((val << 1) ^ (val >> 63)) as u64
+}
+
+/// Reverses `zigzag`
+#[inline]
+pub fn unzigzag(code: u64) -> i64 {
+    ((code >> 1) as i64) ^ -((code & 1) as i64)
+}
+
+/// Writes `val` as groups of 7 bits each followed by a continuation bit.
+///
+/// This is also the layout of `BitWrite::write_len`.
+#[inline]
+pub fn write_varint<W>(w: &mut W, mut val: u64) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    loop {
+        w.write_unsigned(val & 0x7F, 7)?;
+        val >>= 7;
+        w.write_bool(val != 0)?;
+        if val == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads a value written by `write_varint`
+#[inline]
+pub fn read_varint<R>(r: &mut R) -> Result<u64, DeltaError>
+    where R: BitRead + ?Sized
+{
+    let mut val = 0u64;
+    let mut shift = 0;
+    loop {
+        if shift >= 64 {
+            return Err(DeltaError::invalid_data("varint too large"));
+        }
+        let group = r.read_unsigned(7)?;
+        if shift == 63 && group > 1 {
+            return Err(DeltaError::invalid_data("varint too large"));
+        }
+        val |= group << shift;
+        shift += 7;
+        if !r.read_bool()? {
+            return Ok(val);
+        }
+    }
+}
+
+/// Writes `val` using the Elias-gamma code of `val + 1`: the number of
+/// significant bits minus one in unary followed by those bits without
+/// the leading one.
+///
+/// Costs `2 * floor(log2(val + 1)) + 1` bits, so 0 is a single bit.
+#[inline]
+pub fn write_gamma<W>(w: &mut W, val: u64) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    let val = u128::from(val) + 1;
+    let bits = (127 - val.leading_zeros()) as u8;
+    for _ in 0 .. bits {
+        w.write_bool(false)?;
+    }
+    w.write_bool(true)?;
+    w.write_unsigned(val as u64 & mask(bits), bits)
+}
+
+/// Reads a value written by `write_gamma`
+#[inline]
+pub fn read_gamma<R>(r: &mut R) -> Result<u64, DeltaError>
+    where R: BitRead + ?Sized
+{
+    let mut bits = 0u8;
+    while !r.read_bool()? {
+        bits += 1;
+        if bits > 64 {
+            return Err(DeltaError::invalid_data("gamma code too large"));
+        }
+    }
+    let val = (1u128 << bits) | u128::from(r.read_unsigned(bits)?);
+    u64::try_from(val - 1)
+        .map_err(|_| DeltaError::invalid_data("gamma code too large"))
+}
+
+/// Writes `val` using the Golomb-Rice code with parameter `k`: the
+/// quotient `val >> k` in unary followed by the low `k` bits.
+///
+/// Suits values that are usually around `2^k`. Quotients of
+/// `RICE_ESCAPE` and above have the excess sent with `write_gamma` so
+/// that a large outlier can't produce an unbounded run of bits.
+#[inline]
+pub fn write_rice<W>(w: &mut W, val: u64, k: u8) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    assert!(k < 64, "Rice parameter must be below 64");
+    let quotient = val >> k;
+    for _ in 0 .. quotient.min(RICE_ESCAPE) {
+        w.write_bool(true)?;
+    }
+    if quotient >= RICE_ESCAPE {
+        write_gamma(w, quotient - RICE_ESCAPE)?;
+    } else {
+        w.write_bool(false)?;
+    }
+    w.write_unsigned(val & mask(k), k)
+}
+
+/// Reads a value written by `write_rice` with the same `k`
+#[inline]
+pub fn read_rice<R>(r: &mut R, k: u8) -> Result<u64, DeltaError>
+    where R: BitRead + ?Sized
+{
+    assert!(k < 64, "Rice parameter must be below 64");
+    let mut quotient = 0;
+    while quotient < RICE_ESCAPE && r.read_bool()? {
+        quotient += 1;
+    }
+    if quotient == RICE_ESCAPE {
+        quotient = read_gamma(r)?
+            .checked_add(RICE_ESCAPE)
+            .ok_or_else(|| DeltaError::invalid_data("rice code too large"))?;
+    }
+    let high = quotient.checked_shl(u32::from(k))
+        .filter(|_| quotient <= u64::MAX >> k)
+        .ok_or_else(|| DeltaError::invalid_data("rice code too large"))?;
+    Ok(high | r.read_unsigned(k)?)
+}
+
+/// An integer type that can be sent with one of the variable length
+/// codes, signed types are sent through `zigzag`.
+pub trait IntCode: Copy + PartialEq {
+    fn to_code(self) -> u64;
+
+    fn from_code(code: u64) -> Result<Self, DeltaError>;
+
+    /// Returns the code for the wrapping difference from `base`
+    /// treated as a signed value, so a small step either way is a
+    /// small code even for unsigned types.
+    fn diff_code(self, base: Self) -> u64;
+
+    /// Reverses `diff_code`
+    fn from_diff_code(base: Self, code: u64) -> Result<Self, DeltaError>;
+}
+
+macro_rules! impl_int_code {
+    ($($ty:ty => $sty:ty, $to:expr, $from:expr;)*) => {
+    $(
+        impl IntCode for $ty {
+            #[inline]
+            fn to_code(self) -> u64 {
+                let to: fn($ty) -> u64 = $to;
+                to(self)
+            }
+
+            #[inline]
+            fn from_code(code: u64) -> Result<Self, DeltaError> {
+                let from: fn(u64) -> Option<$ty> = $from;
+                from(code).ok_or_else(|| DeltaError::invalid_data("integer code out of range"))
+            }
+
+            #[inline]
+            fn diff_code(self, base: Self) -> u64 {
+                zigzag(self.wrapping_sub(base) as $sty as i64)
+            }
+
+            #[inline]
+            fn from_diff_code(base: Self, code: u64) -> Result<Self, DeltaError> {
+                let diff = <$sty>::try_from(unzigzag(code))
+                    .map_err(|_| DeltaError::invalid_data("integer code out of range"))?;
+                Ok(base.wrapping_add(diff as $ty))
+            }
+        }
+    )*
+    };
+}
+
+// `usize` and `isize` are converted through 64 bits so that the output
+// doesn't depend on the platform.
+impl_int_code! {
+    u8 => i8, u64::from, |v| u8::try_from(v).ok();
+    u16 => i16, u64::from, |v| u16::try_from(v).ok();
+    u32 => i32, u64::from, |v| u32::try_from(v).ok();
+    u64 => i64, |v| v, Some;
+    usize => isize, |v| v as u64, |v| usize::try_from(v).ok();
+    i8 => i8, |v| zigzag(v.into()), |v| i8::try_from(unzigzag(v)).ok();
+    i16 => i16, |v| zigzag(v.into()), |v| i16::try_from(unzigzag(v)).ok();
+    i32 => i32, |v| zigzag(v.into()), |v| i32::try_from(unzigzag(v)).ok();
+    i64 => i64, zigzag, |v| Some(unzigzag(v));
+    isize => isize, |v| zigzag(v as i64), |v| isize::try_from(unzigzag(v)).ok();
+}
+
+macro_rules! impl_code_wrapper {
+    ($($(#[$attr:meta])* $name:ident $(<const $param:ident: u8>)? => ($write:ident, $read:ident $(, $arg:expr)?),)*) => {
+    $(
+        $(#[$attr])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name<T $(, const $param: u8)?>(pub T);
+
+        impl <T $(, const $param: u8)?> DeltaEncodable for $name<T $(, $param)?>
+            where T: IntCode
+        {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if base == Some(self) {
+                    return w.write_bool(false);
+                }
+                w.write_bool(true)?;
+                $write(w, self.0.to_code() $(, $arg)?)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if r.read_bool()? {
+                    Ok($name(T::from_code($read(r $(, $arg)?)?)?))
+                } else {
+                    base.cloned().ok_or_else(DeltaError::missing_baseline)
+                }
+            }
+
+            #[inline]
+            fn encode_changed<W>(&self, _base: &Self, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                $write(w, self.0.to_code() $(, $arg)?)
+            }
+
+            #[inline]
+            fn decode_changed<R>(_base: &Self, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                Ok($name(T::from_code($read(r $(, $arg)?)?)?))
+            }
+        }
+    )*
+    };
+}
+
+impl_code_wrapper! {
+    /// An integer that is sent with `write_varint`
+    Varint => (write_varint, read_varint),
+    /// An integer that is sent with `write_gamma`
+    Gamma => (write_gamma, read_gamma),
+    /// An integer that is sent with `write_rice` using `K` as the parameter
+    Rice<const K: u8> => (write_rice, read_rice, Self::K),
+}
+
+impl <T, const K: u8> Rice<T, K> {
+    /// `K`, failing to compile if it is too large for `write_rice`
+    const K: u8 = {
+        assert!(K < 64, "Rice parameter must be below 64");
+        K
+    };
+}
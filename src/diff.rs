@@ -0,0 +1,215 @@
+use super::*;
+
+/// Above this many cells the longest common subsequence isn't searched
+/// for and the differing middle is treated as a single replacement.
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// A `Vec` that is encoded as an edit script against its base.
+///
+/// Unlike `Vec<T>`, which pairs elements by index, inserting or removing
+/// an element only costs the edit itself. Changed elements are sent as a
+/// delta against the element they replace.
+#[derive(Debug, PartialOrd, PartialEq, Clone)]
+pub struct DiffVec<T>(pub Vec<T>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    /// Copy elements from the base
+    Keep = 0,
+    /// Skip elements in the base
+    Remove = 1,
+    /// Decode new elements
+    Insert = 2,
+    /// Decode elements against the matching base elements
+    Update = 3,
+}
+
+impl Edit {
+    fn from_tag(tag: u64) -> Edit {
+        match tag {
+            0 => Edit::Keep,
+            1 => Edit::Remove,
+            2 => Edit::Insert,
+            _ => Edit::Update,
+        }
+    }
+}
+
+fn push_run(runs: &mut Vec<(Edit, usize)>, edit: Edit, count: usize) {
+    if count == 0 {
+        return;
+    }
+    match runs.last_mut() {
+        Some(last) if last.0 == edit => last.1 += count,
+        _ => runs.push((edit, count)),
+    }
+}
+
+/// Pairs up removals and insertions between two kept runs as updates
+fn flush_changes(runs: &mut Vec<(Edit, usize)>, removed: &mut usize, inserted: &mut usize) {
+    let updated = (*removed).min(*inserted);
+    push_run(runs, Edit::Update, updated);
+    push_run(runs, Edit::Remove, *removed - updated);
+    push_run(runs, Edit::Insert, *inserted - updated);
+    *removed = 0;
+    *inserted = 0;
+}
+
+fn edit_script<T>(base: &[T], val: &[T]) -> Vec<(Edit, usize)>
+    where T: PartialEq
+{
+    let prefix = base.iter()
+        .zip(val)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = base[prefix ..].iter().rev()
+        .zip(val[prefix ..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let base_mid = &base[prefix .. base.len() - suffix];
+    let val_mid = &val[prefix .. val.len() - suffix];
+
+    let mut runs = vec![];
+    push_run(&mut runs, Edit::Keep, prefix);
+
+    let mut removed = 0;
+    let mut inserted = 0;
+    let cells = (base_mid.len() + 1).saturating_mul(val_mid.len() + 1);
+    if cells <= MAX_LCS_CELLS {
+        // lcs[i][j] is the length of the longest common subsequence
+        // of base_mid[i..] and val_mid[j..]
+        let width = val_mid.len() + 1;
+        let mut lcs = vec![0u32; cells];
+        for i in (0 .. base_mid.len()).rev() {
+            for j in (0 .. val_mid.len()).rev() {
+                lcs[i * width + j] = if base_mid[i] == val_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < base_mid.len() && j < val_mid.len() {
+            if base_mid[i] == val_mid[j] {
+                flush_changes(&mut runs, &mut removed, &mut inserted);
+                push_run(&mut runs, Edit::Keep, 1);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                removed += 1;
+                i += 1;
+            } else {
+                inserted += 1;
+                j += 1;
+            }
+        }
+        removed += base_mid.len() - i;
+        inserted += val_mid.len() - j;
+    } else {
+        removed = base_mid.len();
+        inserted = val_mid.len();
+    }
+    flush_changes(&mut runs, &mut removed, &mut inserted);
+
+    push_run(&mut runs, Edit::Keep, suffix);
+    runs
+}
+
+impl <T> DeltaEncodable for DiffVec<T>
+    where T: DeltaEncodable + PartialEq + Clone
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        if let Some(base) = base {
+            if base == self {
+                w.write_bool(false)?;
+                return Ok(())
+            }
+        }
+        w.write_bool(true)?;
+
+        let base = base.map_or(&[][..], |v| &v.0[..]);
+        let runs = edit_script(base, &self.0);
+        w.write_len(runs.len())?;
+
+        let (mut base_idx, mut idx) = (0, 0);
+        for (edit, count) in runs {
+            w.write_unsigned(edit as u64, 2)?;
+            w.write_len(count)?;
+            match edit {
+                Edit::Keep => {
+                    base_idx += count;
+                    idx += count;
+                },
+                Edit::Remove => base_idx += count,
+                Edit::Insert => {
+                    for idx in idx .. idx + count {
+                        T::encode(&self.0[idx], None, w)
+                            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+                    }
+                    idx += count;
+                },
+                Edit::Update => {
+                    for offset in 0 .. count {
+                        T::encode(&self.0[idx + offset], Some(&base[base_idx + offset]), w)
+                            .map_err(|e| e.within(PathSegment::Index(idx + offset)))?;
+                    }
+                    base_idx += count;
+                    idx += count;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        if !r.read_bool()? {
+            return base.cloned().ok_or_else(DeltaError::missing_baseline);
+        }
+
+        let runs = r.read_len()?;
+        let mut buf = Vec::new();
+        let mut base_idx = 0;
+        for _ in 0 .. runs {
+            let edit = Edit::from_tag(r.read_unsigned(2)?);
+            let count = r.read_len()?;
+            if edit != Edit::Insert {
+                let base = base.ok_or_else(DeltaError::missing_baseline)?;
+                if base.0.len() - base_idx < count {
+                    return Err(DeltaError::invalid_data("edit script doesn't match the base"));
+                }
+            }
+            match edit {
+                Edit::Keep => {
+                    let base = &base.unwrap().0;
+                    buf.extend_from_slice(&base[base_idx .. base_idx + count]);
+                    base_idx += count;
+                },
+                Edit::Remove => base_idx += count,
+                Edit::Insert => {
+                    for _ in 0 .. count {
+                        let idx = buf.len();
+                        buf.push(T::decode(None, r)
+                            .map_err(|e| e.within(PathSegment::Index(idx)))?);
+                    }
+                },
+                Edit::Update => {
+                    let base = &base.unwrap().0;
+                    for old in &base[base_idx .. base_idx + count] {
+                        let idx = buf.len();
+                        buf.push(T::decode(Some(old), r)
+                            .map_err(|e| e.within(PathSegment::Index(idx)))?);
+                    }
+                    base_idx += count;
+                },
+            }
+        }
+        Ok(DiffVec(buf))
+    }
+}
@@ -0,0 +1,141 @@
+use super::*;
+use quantize::sqrt;
+
+/// The number of bits per component used by `UnitVector3` unless
+/// specified otherwise
+pub const DEFAULT_DIRECTION_BITS: u8 = 12;
+
+/// A 3D vector type that can be sent as a direction with
+/// `encode_direction`
+pub trait Direction3: Sized {
+    fn to_xyz(&self) -> [f64; 3];
+
+    fn from_xyz(xyz: [f64; 3]) -> Self;
+}
+
+impl Direction3 for [f32; 3] {
+    #[inline]
+    fn to_xyz(&self) -> [f64; 3] {
+        self.map(f64::from)
+    }
+
+    #[inline]
+    fn from_xyz(xyz: [f64; 3]) -> Self {
+        xyz.map(|v| v as f32)
+    }
+}
+
+impl Direction3 for [f64; 3] {
+    #[inline]
+    fn to_xyz(&self) -> [f64; 3] {
+        *self
+    }
+
+    #[inline]
+    fn from_xyz(xyz: [f64; 3]) -> Self {
+        xyz
+    }
+}
+
+#[inline]
+fn sign(val: f64) -> f64 {
+    if val < 0.0 { -1.0 } else { 1.0 }
+}
+
+/// Projects the direction onto an octahedron and unfolds it onto a
+/// square, returning the quantized position on the square.
+fn pack(v: [f64; 3], bits: u8) -> [i64; 2] {
+    let len = v[0].abs() + v[1].abs() + v[2].abs();
+    let [x, y, z] = if len > 0.0 && len.is_finite() {
+        v.map(|v| v / len)
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    let (x, y) = if z < 0.0 {
+        // Fold the lower half over the diagonals
+        ((1.0 - y.abs()) * sign(x), (1.0 - x.abs()) * sign(y))
+    } else {
+        (x, y)
+    };
+    [quantize_signed(x, 1.0, bits), quantize_signed(y, 1.0, bits)]
+}
+
+fn unpack(packed: [i64; 2], bits: u8) -> [f64; 3] {
+    let x = dequantize_signed(packed[0], 1.0, bits);
+    let y = dequantize_signed(packed[1], 1.0, bits);
+    let z = 1.0 - x.abs() - y.abs();
+    let (x, y) = if z < 0.0 {
+        ((1.0 - y.abs()) * sign(x), (1.0 - x.abs()) * sign(y))
+    } else {
+        (x, y)
+    };
+    let len = sqrt(x * x + y * y + z * z);
+    [x / len, y / len, z / len]
+}
+
+/// Writes a direction using an octahedral mapping to two components
+/// quantized to `bits` bits each, which must be at least 2 so that a
+/// component keeps its sign.
+///
+/// The vector is normalized first. Against a base a single bit is sent
+/// if both quantize to the same value.
+pub fn encode_direction<D, W>(val: &D, base: Option<&D>, bits: u8, w: &mut W) -> Result<(), DeltaError>
+    where D: Direction3,
+          W: BitWrite
+{
+    debug_assert!(bits >= 2, "Direction components need at least 2 bits");
+    let packed = pack(val.to_xyz(), bits);
+    if let Some(base) = base {
+        if pack(base.to_xyz(), bits) == packed {
+            return w.write_bool(false);
+        }
+        w.write_bool(true)?;
+    }
+    w.write_signed(packed[0], bits)?;
+    w.write_signed(packed[1], bits)
+}
+
+/// Reads a direction written by `encode_direction`
+pub fn decode_direction<D, R>(base: Option<&D>, bits: u8, r: &mut R) -> Result<D, DeltaError>
+    where D: Direction3 + Clone,
+          R: BitRead
+{
+    debug_assert!(bits >= 2, "Direction components need at least 2 bits");
+    if let Some(base) = base {
+        if !r.read_bool()? {
+            return Ok(base.clone());
+        }
+    }
+    let packed = [r.read_signed(bits)?, r.read_signed(bits)?];
+    Ok(D::from_xyz(unpack(packed, bits)))
+}
+
+/// A unit vector that is sent as a direction using `encode_direction`
+/// with `BITS` bits per component.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct UnitVector3<T, const BITS: u8 = DEFAULT_DIRECTION_BITS>(pub T);
+
+impl <T, const BITS: u8> UnitVector3<T, BITS> {
+    /// Fails to compile when used with too few bits
+    const VALID_BITS: () = assert!(BITS >= 2, "UnitVector3 needs at least 2 bits per component");
+}
+
+impl <T, const BITS: u8> DeltaEncodable for UnitVector3<T, BITS>
+    where T: Direction3 + Clone
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        let () = Self::VALID_BITS;
+        encode_direction(&self.0, base.map(|v| &v.0), BITS, w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        let () = Self::VALID_BITS;
+        decode_direction(base.map(|v| &v.0), BITS, r).map(UnitVector3)
+    }
+}
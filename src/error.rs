@@ -0,0 +1,210 @@
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+/// An error that occurred while encoding or decoding a value.
+///
+/// Every variant carries the path to the field that failed so that
+/// it can be reported, e.g. `Player.inventory[3].count`.
+#[derive(Debug)]
+pub enum DeltaError {
+    /// The value was delta encoded but no matching baseline was
+    /// provided to decode against.
+    MissingBaseline { path: FieldPath },
+    /// An enum variant or selector tag that doesn't exist was read.
+    InvalidVariant { tag: u64, path: FieldPath },
+    /// The value doesn't fit in the number of bits the field allows.
+    OutOfRange { value: i128, bits: u8, path: FieldPath },
+    /// The input ended before the value was fully read.
+    Truncated { path: FieldPath },
+    /// The input was well formed but contained an invalid value.
+    InvalidData { reason: &'static str, path: FieldPath },
+    /// The underlying reader or writer failed.
+    #[cfg(feature = "std")]
+    Io { error: io::Error, path: FieldPath },
+}
+
+impl DeltaError {
+    pub fn missing_baseline() -> DeltaError {
+        DeltaError::MissingBaseline { path: FieldPath::default() }
+    }
+
+    pub fn invalid_variant(tag: u64) -> DeltaError {
+        DeltaError::InvalidVariant { tag, path: FieldPath::default() }
+    }
+
+    pub fn out_of_range(value: i128, bits: u8) -> DeltaError {
+        DeltaError::OutOfRange { value, bits, path: FieldPath::default() }
+    }
+
+    pub fn truncated() -> DeltaError {
+        DeltaError::Truncated { path: FieldPath::default() }
+    }
+
+    pub fn invalid_data(reason: &'static str) -> DeltaError {
+        DeltaError::InvalidData { reason, path: FieldPath::default() }
+    }
+
+    /// Returns the path to the field that caused the error
+    pub fn path(&self) -> &FieldPath {
+        match *self {
+            DeltaError::MissingBaseline { ref path }
+            | DeltaError::InvalidVariant { ref path, .. }
+            | DeltaError::OutOfRange { ref path, .. }
+            | DeltaError::Truncated { ref path }
+            | DeltaError::InvalidData { ref path, .. } => path,
+            #[cfg(feature = "std")]
+            DeltaError::Io { ref path, .. } => path,
+        }
+    }
+
+    fn path_mut(&mut self) -> &mut FieldPath {
+        match *self {
+            DeltaError::MissingBaseline { ref mut path }
+            | DeltaError::InvalidVariant { ref mut path, .. }
+            | DeltaError::OutOfRange { ref mut path, .. }
+            | DeltaError::Truncated { ref mut path }
+            | DeltaError::InvalidData { ref mut path, .. } => path,
+            #[cfg(feature = "std")]
+            DeltaError::Io { ref mut path, .. } => path,
+        }
+    }
+
+    /// Marks the error as having happened inside the given segment
+    pub fn within(mut self, segment: PathSegment) -> DeltaError {
+        self.path_mut().push(segment);
+        self
+    }
+
+    /// Marks the error as having happened inside the named type.
+    ///
+    /// The outermost type wins as the error propagates.
+    pub fn in_type(mut self, name: &'static str) -> DeltaError {
+        self.path_mut().root = Some(name);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for DeltaError {
+    fn from(error: io::Error) -> DeltaError {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            DeltaError::truncated()
+        } else {
+            DeltaError::Io { error, path: FieldPath::default() }
+        }
+    }
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeltaError::MissingBaseline { .. } => write!(f, "missing baseline")?,
+            DeltaError::InvalidVariant { tag, .. } => write!(f, "invalid variant tag {}", tag)?,
+            DeltaError::OutOfRange { value, bits, .. } => write!(f, "value {} doesn't fit in {} bits", value, bits)?,
+            DeltaError::Truncated { .. } => write!(f, "input truncated")?,
+            DeltaError::InvalidData { reason, .. } => write!(f, "invalid data: {}", reason)?,
+            #[cfg(feature = "std")]
+            DeltaError::Io { ref error, .. } => write!(f, "io error: {}", error)?,
+        }
+        let path = self.path();
+        if !path.is_empty() {
+            write!(f, " at {}", path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeltaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DeltaError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A single step in a `FieldPath`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+    Variant(&'static str),
+}
+
+/// The location of a field within a value.
+///
+/// Only the root type name is tracked without the `alloc` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldPath {
+    root: Option<&'static str>,
+    // Stored innermost first as segments are added while the
+    // error propagates outwards
+    #[cfg(feature = "alloc")]
+    segments: Vec<PathSegment>,
+}
+
+impl FieldPath {
+    /// The name of the outermost type, if known
+    pub fn root(&self) -> Option<&'static str> {
+        self.root
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none() && self.segments().next().is_none()
+    }
+
+    /// Iterates over the segments from the outermost inwards
+    #[cfg(feature = "alloc")]
+    pub fn segments(&self) -> impl Iterator<Item = PathSegment> + '_ {
+        self.segments.iter().rev().cloned()
+    }
+
+    /// Iterates over the segments from the outermost inwards
+    #[cfg(not(feature = "alloc"))]
+    pub fn segments(&self) -> impl Iterator<Item = PathSegment> + '_ {
+        core::iter::empty()
+    }
+
+    #[cfg(feature = "alloc")]
+    fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn push(&mut self, _segment: PathSegment) {}
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        if let Some(root) = self.root {
+            write!(f, "{}", root)?;
+            first = false;
+        }
+        for segment in self.segments() {
+            match segment {
+                PathSegment::Field(name) if first => write!(f, "{}", name)?,
+                PathSegment::Field(name) => write!(f, ".{}", name)?,
+                PathSegment::Index(idx) => write!(f, "[{}]", idx)?,
+                PathSegment::Variant(name) => write!(f, "::{}", name)?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `func` attributing any error it returns to `segment`.
+///
+/// Used by the generated code to build field paths.
+#[doc(hidden)]
+#[inline]
+pub fn __within<T, F>(segment: PathSegment, func: F) -> Result<T, DeltaError>
+    where F: FnOnce() -> Result<T, DeltaError>
+{
+    func().map_err(|e| e.within(segment))
+}
@@ -0,0 +1,183 @@
+use super::*;
+
+const COMPONENTS: [&str; 4] = ["x", "y", "z", "w"];
+
+// Against a base a single bit is sent when the value is unchanged,
+// otherwise each component is sent with its own change bit.
+macro_rules! impl_glam_vector {
+    ($($name:ident: [$s:ty; $len:expr],)*) => {
+    $(
+        impl DeltaEncodable for glam::$name {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if let Some(base) = base {
+                    if base == self {
+                        return w.write_bool(false);
+                    }
+                    w.write_bool(true)?;
+                }
+                let base = base.map(|v| v.to_array());
+                for (idx, val) in self.to_array().iter().enumerate() {
+                    <$s>::encode(val, base.as_ref().map(|v| &v[idx]), w)
+                        .map_err(|e| e.within(PathSegment::Field(COMPONENTS[idx])))?;
+                }
+                Ok(())
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if let Some(base) = base {
+                    if !r.read_bool()? {
+                        return Ok(*base);
+                    }
+                }
+                let base = base.map(|v| v.to_array());
+                let vals = <[$s; $len]>::create(|idx| {
+                    <$s>::decode(base.as_ref().map(|v| &v[idx]), r)
+                        .map_err(|e| e.within(PathSegment::Field(COMPONENTS[idx])))
+                })?;
+                Ok(glam::$name::from_array(vals))
+            }
+        }
+    )*
+    };
+}
+
+impl_glam_vector!(
+    Vec2: [f32; 2],
+    Vec3: [f32; 3],
+    Vec3A: [f32; 3],
+    Vec4: [f32; 4],
+    DVec2: [f64; 2],
+    DVec3: [f64; 3],
+    DVec4: [f64; 4],
+    IVec2: [i32; 2],
+    IVec3: [i32; 3],
+    IVec4: [i32; 4],
+    UVec2: [u32; 2],
+    UVec3: [u32; 3],
+    UVec4: [u32; 4],
+);
+
+// Matrices and transforms delegate to their columns, each of which has
+// its own unchanged bit.
+macro_rules! impl_glam_struct {
+    ($($name:ident { $($field:ident: $fty:ident),* },)*) => {
+    $(
+        impl DeltaEncodable for glam::$name {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if let Some(base) = base {
+                    if base == self {
+                        return w.write_bool(false);
+                    }
+                    w.write_bool(true)?;
+                }
+                $(
+                    glam::$fty::encode(&self.$field, base.map(|v| &v.$field), w)
+                        .map_err(|e| e.within(PathSegment::Field(stringify!($field))))?;
+                )*
+                Ok(())
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if let Some(base) = base {
+                    if !r.read_bool()? {
+                        return Ok(*base);
+                    }
+                }
+                Ok(glam::$name {
+                    $(
+                        $field: glam::$fty::decode(base.map(|v| &v.$field), r)
+                            .map_err(|e| e.within(PathSegment::Field(stringify!($field))))?,
+                    )*
+                })
+            }
+        }
+    )*
+    };
+}
+
+impl_glam_struct!(
+    Mat3 { x_axis: Vec3, y_axis: Vec3, z_axis: Vec3 },
+    Mat3A { x_axis: Vec3A, y_axis: Vec3A, z_axis: Vec3A },
+    Mat4 { x_axis: Vec4, y_axis: Vec4, z_axis: Vec4, w_axis: Vec4 },
+    Affine3A { matrix3: Mat3A, translation: Vec3A },
+    DMat3 { x_axis: DVec3, y_axis: DVec3, z_axis: DVec3 },
+    DMat4 { x_axis: DVec4, y_axis: DVec4, z_axis: DVec4, w_axis: DVec4 },
+    DAffine3 { matrix3: DMat3, translation: DVec3 },
+);
+
+macro_rules! impl_glam_quat {
+    ($($name:ident: $s:ty,)*) => {
+    $(
+        impl QuatComponents for glam::$name {
+            #[inline]
+            fn to_xyzw(&self) -> [f64; 4] {
+                self.to_array().map(f64::from)
+            }
+
+            #[inline]
+            fn from_xyzw(xyzw: [f64; 4]) -> Self {
+                glam::$name::from_array(xyzw.map(|v| v as $s))
+            }
+        }
+
+        /// Sent as a normalized rotation using `encode_quat` with
+        /// `DEFAULT_QUAT_BITS` bits per component
+        impl DeltaEncodable for glam::$name {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                encode_quat(self, base, DEFAULT_QUAT_BITS, w)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                decode_quat(base, DEFAULT_QUAT_BITS, r)
+            }
+        }
+    )*
+    };
+}
+
+impl_glam_quat!(
+    Quat: f32,
+    DQuat: f64,
+);
+
+macro_rules! impl_glam_direction {
+    ($($name:ident: $s:ty,)*) => {
+    $(
+        impl Direction3 for glam::$name {
+            #[inline]
+            fn to_xyz(&self) -> [f64; 3] {
+                self.to_array().map(f64::from)
+            }
+
+            #[inline]
+            fn from_xyz(xyz: [f64; 3]) -> Self {
+                glam::$name::from_array(xyz.map(|v| v as $s))
+            }
+        }
+    )*
+    };
+}
+
+impl_glam_direction!(
+    Vec3: f32,
+    Vec3A: f32,
+    DVec3: f64,
+);
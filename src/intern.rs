@@ -0,0 +1,264 @@
+use super::*;
+use alloc::collections::BTreeMap;
+use core::convert::TryFrom;
+
+/// Strings shared between the writer and the reader so that
+/// `#[delta_interned]` fields can send an id instead of the text.
+///
+/// The table only ever grows. It is replicated like any other state:
+/// encoding it against the previous copy sent only sends the strings
+/// added since. Once both sides have the same table, wrap the writer in
+/// an `InternWriter` and the reader in an `InternReader` to use it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StringTable {
+    strings: Vec<Arc<str>>,
+    ids: BTreeMap<Arc<str>, u32>,
+}
+
+impl StringTable {
+    pub fn new() -> StringTable {
+        StringTable::default()
+    }
+
+    /// Returns the id of `val`, adding it to the table if needed
+    pub fn intern(&mut self, val: &str) -> u32 {
+        if let Some(id) = self.id(val) {
+            return id;
+        }
+        let id = self.strings.len() as u32;
+        let val: Arc<str> = val.into();
+        self.strings.push(val.clone());
+        self.ids.insert(val, id);
+        id
+    }
+
+    /// Returns the id of `val` if it is in the table
+    pub fn id(&self, val: &str) -> Option<u32> {
+        self.ids.get(val).cloned()
+    }
+
+    /// Returns the string with the given id
+    pub fn get(&self, id: u32) -> Option<&Arc<str>> {
+        self.strings.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.strings.iter().map(|v| &**v)
+    }
+
+    fn push_decoded(&mut self, val: String) -> Result<(), DeltaError> {
+        if self.ids.contains_key(&*val) {
+            return Err(DeltaError::invalid_data("duplicate string in table"));
+        }
+        self.intern(&val);
+        Ok(())
+    }
+}
+
+/// Against a base that it extends only the new strings are sent,
+/// otherwise the whole table is.
+impl DeltaEncodable for StringTable {
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        let mut start = 0;
+        if let Some(base) = base {
+            if base == self {
+                return w.write_bool(false);
+            }
+            w.write_bool(true)?;
+            let extends = base.len() <= self.len()
+                && base.strings.iter().zip(&self.strings).all(|(a, b)| a == b);
+            w.write_bool(extends)?;
+            if extends {
+                start = base.len();
+            }
+        }
+        w.write_len(self.len() - start)?;
+        for (idx, val) in self.strings.iter().enumerate().skip(start) {
+            write_bytes(w, val.as_bytes())
+                .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        let mut table = StringTable::new();
+        if let Some(base) = base {
+            if !r.read_bool()? {
+                return Ok(base.clone());
+            }
+            if r.read_bool()? {
+                table = base.clone();
+            }
+        }
+        let count = r.read_len()?;
+        for _ in 0 .. count {
+            let idx = table.len();
+            let mut buf = Vec::new();
+            read_bytes(r, &mut buf)
+                .and_then(|_| String::from_utf8(buf)
+                    .map_err(|_| DeltaError::invalid_data("invalid utf-8")))
+                .and_then(|v| table.push_decoded(v))
+                .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        }
+        Ok(table)
+    }
+}
+
+/// Wraps a `BitWrite` to give `#[delta_interned]` fields access to a
+/// `StringTable`. Everything else is passed through unchanged.
+///
+/// Wrapping a `BitCounter` gives the exact size of values with interned
+/// fields, which `DeltaEncodable::encoded_bits` counts as text.
+pub struct InternWriter<'a, W> {
+    inner: W,
+    table: &'a StringTable,
+}
+
+impl <'a, W> InternWriter<'a, W>
+    where W: BitWrite
+{
+    pub fn new(inner: W, table: &'a StringTable) -> InternWriter<'a, W> {
+        InternWriter { inner, table }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl <W> BitWrite for InternWriter<'_, W>
+    where W: BitWrite
+{
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> { self.inner.write_bool(val) }
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> { self.inner.write_unsigned(val, bits) }
+    #[inline]
+    fn write_signed(&mut self, val: i64, bits: u8) -> Result<(), DeltaError> { self.inner.write_signed(val, bits) }
+    #[inline]
+    fn write_f32(&mut self, val: f32) -> Result<(), DeltaError> { self.inner.write_f32(val) }
+    #[inline]
+    fn write_f64(&mut self, val: f64) -> Result<(), DeltaError> { self.inner.write_f64(val) }
+    #[inline]
+    fn enter_context(&mut self, id: u32) { self.inner.enter_context(id) }
+    #[inline]
+    fn leave_context(&mut self) { self.inner.leave_context() }
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) { self.inner.enter_model(model) }
+    #[inline]
+    fn leave_model(&mut self) { self.inner.leave_model() }
+    #[inline]
+    fn string_id(&self, val: &str) -> Option<u32> { self.table.id(val) }
+    #[inline]
+    fn write_len(&mut self, len: usize) -> Result<(), DeltaError> { self.inner.write_len(len) }
+    #[inline]
+    fn write_str(&mut self, val: &str, base: Option<&str>) -> Result<(), DeltaError> { self.inner.write_str(val, base) }
+}
+
+/// Wraps a `BitRead` to give `#[delta_interned]` fields access to a
+/// `StringTable`. Everything else is passed through unchanged.
+pub struct InternReader<'a, R> {
+    inner: R,
+    table: &'a StringTable,
+}
+
+impl <'a, R> InternReader<'a, R>
+    where R: BitRead
+{
+    pub fn new(inner: R, table: &'a StringTable) -> InternReader<'a, R> {
+        InternReader { inner, table }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl <R> BitRead for InternReader<'_, R>
+    where R: BitRead
+{
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, DeltaError> { self.inner.read_bool() }
+    #[inline]
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> { self.inner.read_unsigned(bits) }
+    #[inline]
+    fn read_signed(&mut self, bits: u8) -> Result<i64, DeltaError> { self.inner.read_signed(bits) }
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32, DeltaError> { self.inner.read_f32() }
+    #[inline]
+    fn read_f64(&mut self) -> Result<f64, DeltaError> { self.inner.read_f64() }
+    #[inline]
+    fn enter_context(&mut self, id: u32) { self.inner.enter_context(id) }
+    #[inline]
+    fn leave_context(&mut self) { self.inner.leave_context() }
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) { self.inner.enter_model(model) }
+    #[inline]
+    fn leave_model(&mut self) { self.inner.leave_model() }
+    #[inline]
+    fn interned_string(&self, id: u32) -> Option<Arc<str>> { self.table.get(id).cloned() }
+    #[inline]
+    fn read_len(&mut self) -> Result<usize, DeltaError> { self.inner.read_len() }
+    #[inline]
+    fn read_string(&mut self, base: Option<&str>) -> Result<String, DeltaError> { self.inner.read_string(base) }
+}
+
+/// Writes a string for a `#[delta_interned]` field, sending its id if the
+/// writer's table has it and the text otherwise.
+///
+/// Only a single bit is sent if it matches `base`.
+#[inline]
+pub fn encode_interned<W>(val: &str, base: Option<&str>, w: &mut W) -> Result<(), DeltaError>
+    where W: BitWrite + ?Sized
+{
+    if base == Some(val) {
+        return w.write_bool(false);
+    }
+    w.write_bool(true)?;
+    match w.string_id(val) {
+        Some(id) => {
+            w.write_bool(true)?;
+            w.write_len(id as usize)
+        },
+        None => {
+            w.write_bool(false)?;
+            write_changed_str(w, val, base)
+        },
+    }
+}
+
+/// Reads a string written by `encode_interned`
+#[inline]
+pub fn decode_interned<R>(base: Option<&str>, r: &mut R) -> Result<String, DeltaError>
+    where R: BitRead + ?Sized
+{
+    if !r.read_bool()? {
+        return base
+            .map(|v| v.to_owned())
+            .ok_or_else(DeltaError::missing_baseline);
+    }
+    if r.read_bool()? {
+        let id = r.read_len()?;
+        let id = u32::try_from(id)
+            .map_err(|_| DeltaError::invalid_data("unknown string id"))?;
+        r.interned_string(id)
+            .map(|v| String::from(&*v))
+            .ok_or_else(|| DeltaError::invalid_data("unknown string id"))
+    } else {
+        read_changed_str(r, base)
+    }
+}
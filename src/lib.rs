@@ -1,182 +1,463 @@
 
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[cfg(feature="cgmath")]
 mod cgmath_support;
+#[cfg(feature = "glam")]
+mod glam_support;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_support;
+mod bits;
+mod error;
+mod slice;
+mod changes;
+mod quantize;
+mod quat;
+mod direction;
+mod codes;
+mod model;
+#[cfg(feature = "alloc")]
+mod baseline;
+#[cfg(feature = "alloc")]
+mod map;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "alloc")]
+mod intern;
+#[cfg(feature = "range_coder")]
+mod range;
 
 pub use delta_encode_derive::*;
+#[cfg(feature = "std")]
 pub use think_bitio as bitio;
+pub use bits::*;
+pub use error::*;
+pub use slice::*;
+pub use changes::*;
+pub use quantize::*;
+pub use quat::*;
+pub use direction::*;
+pub use codes::*;
+pub use model::*;
+#[cfg(feature = "alloc")]
+pub use baseline::*;
+#[cfg(feature = "alloc")]
+pub use diff::*;
+#[cfg(feature = "alloc")]
+pub use intern::*;
+#[cfg(feature = "range_coder")]
+pub use range::*;
 
-use bitio::*;
-use std::io::{self, Read, Write};
-use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+#[cfg(feature = "alloc")]
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
 
 pub trait DeltaEncodable: Sized {
 
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write;
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite;
+
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead;
+
+    /// Encodes a value that is already known to differ from `base`, such
+    /// as a changed element of an array.
+    ///
+    /// Types that start with a bit saying whether they changed skip it
+    /// here, by default this is the same as `encode` against `base`.
+    #[inline]
+    fn encode_changed<W>(&self, base: &Self, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.encode(Some(base), w)
+    }
+
+    /// Reads a value written by `encode_changed`
+    #[inline]
+    fn decode_changed<R>(base: &Self, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        Self::decode(Some(base), r)
+    }
 
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read;
+    /// Returns the number of bits `encode` would write for this value
+    /// without producing any output.
+    ///
+    /// If encoding would fail this is the number of bits written before
+    /// the failure.
+    ///
+    /// No `StringTable` is shared with the counter so `#[delta_interned]`
+    /// fields are counted as text even when an id would be sent. Encode
+    /// into an `InternWriter` wrapping a `BitCounter` to count them
+    /// exactly.
+    #[inline]
+    fn encoded_bits(&self, base: Option<&Self>) -> usize {
+        let mut counter = BitCounter::new();
+        let _ = self.encode(base, &mut counter);
+        counter.bits()
+    }
 }
 
-impl <T> DeltaEncodable for Arc<T>
-    where T: DeltaEncodable
+macro_rules! impl_pointer {
+    ($($ptr:ident,)*) => {
+    $(
+        #[cfg(feature = "alloc")]
+        impl <T> DeltaEncodable for $ptr<T>
+            where T: DeltaEncodable
+        {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                T::encode(self, base.map(|v| &**v), w)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                Ok($ptr::new(T::decode(base.map(|v| &**v), r)?))
+            }
+        }
+    )*
+    };
+}
+
+impl_pointer!(Arc, Rc, Box,);
+
+/// Always decodes into `Cow::Owned`
+#[cfg(feature = "alloc")]
+impl <T> DeltaEncodable for Cow<'static, T>
+    where T: DeltaEncodable + Clone
 {
     #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
         T::encode(self, base.map(|v| &**v), w)
     }
 
     #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        Ok(Arc::new(T::decode(base.map(|v| &**v), r)?))
+        Ok(Cow::Owned(T::decode(base.map(|v| &**v), r)?))
     }
 }
 
+macro_rules! impl_tuple {
+    ($(($($idx:tt $ty:ident),*),)*) => {
+    $(
+        impl <$($ty),*> DeltaEncodable for ($($ty,)*)
+            where $($ty: DeltaEncodable),*
+        {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                $(
+                    $ty::encode(&self.$idx, base.map(|v| &v.$idx), w)
+                        .map_err(|e| e.within(PathSegment::Index($idx)))?;
+                )*
+                Ok(())
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                Ok(($(
+                    $ty::decode(base.map(|v| &v.$idx), r)
+                        .map_err(|e| e.within(PathSegment::Index($idx)))?,
+                )*))
+            }
+        }
+    )*
+    };
+}
+
+impl_tuple!(
+    (0 A),
+    (0 A, 1 B),
+    (0 A, 1 B, 2 C),
+    (0 A, 1 B, 2 C, 3 D),
+    (0 A, 1 B, 2 C, 3 D, 4 E),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K),
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L),
+);
+
+/// Builds an array by calling `init_func` for each index in order,
+/// stopping at the first error.
 pub trait CreateArray<T>: Sized {
     fn create<'a, F, E>(init_func: F) -> Result<Self, E>
         where F: FnMut(usize) -> Result<T, E> + 'a;
 }
 
-macro_rules! impl_create_array {
-    (
-        [$size:expr] $first:ident, $($var:ident,)*
-    ) => {
-        impl <T> CreateArray<T> for [T; $size] {
-            #[allow(unused_variables, unused_mut, unused_assignments, clippy::eval_order_dependence)]
-            fn create<'a, F, E>(mut init_func: F) -> Result<Self, E>
-                where F: FnMut(usize) -> Result<T, E> + 'a
-            {
-                let mut offset = 1;
-                Ok([
-                    init_func(0)?,
-                $({
-                    let $var = init_func(offset)?;
-                    offset += 1;
-                    $var
-                }),*
-                ])
+impl <T, const N: usize> CreateArray<T> for [T; N] {
+    #[inline]
+    fn create<'a, F, E>(mut init_func: F) -> Result<Self, E>
+        where F: FnMut(usize) -> Result<T, E> + 'a
+    {
+        let mut error = None;
+        let vals: [Option<T>; N] = core::array::from_fn(|idx| {
+            if error.is_some() {
+                return None;
             }
+            init_func(idx)
+                .map_err(|e| error = Some(e))
+                .ok()
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(vals.map(|v| v.expect("Missing array element"))),
         }
-        impl_create_array!([$size - 1] $($var,)*);
-    };
-    ([$size:expr]) => {};
+    }
 }
 
-
-impl DeltaEncodable for String {
+/// Against a base a single bit is sent if nothing changed, otherwise
+/// the changed elements are sent with `encode_changed` against their
+/// base element. Finding the changes needs the elements to be
+/// `PartialEq` and `Clone`.
+impl <T, const N: usize> DeltaEncodable for [T; N]
+    where T: DeltaEncodable + PartialEq + Clone
+{
     #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
-        encode_str(self, base.map(|v| v.as_str()), w)
+        if let Some(base) = base {
+            if base == self {
+                return w.write_bool(false);
+            }
+            w.write_bool(true)?;
+            return write_changes(w, N, |idx| self[idx] != base[idx], |w, idx| {
+                T::encode_changed(&self[idx], &base[idx], w)
+                    .map_err(|e| e.within(PathSegment::Index(idx)))
+            });
+        }
+        for (idx, val) in self.iter().enumerate() {
+            T::encode(val, None, w)
+                .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        }
+        Ok(())
     }
 
     #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        decode_string(base.map(|v| v.as_str()), r)
+        if let Some(base) = base {
+            if !r.read_bool()? {
+                return Ok(base.clone());
+            }
+            let mut changes = ChangeReader::new(r, N)?;
+            return Self::create(|idx| {
+                if changes.changed(r, idx)? {
+                    T::decode_changed(&base[idx], r)
+                        .map_err(|e| e.within(PathSegment::Index(idx)))
+                } else {
+                    Ok(base[idx].clone())
+                }
+            });
+        }
+        Self::create(|idx| {
+            T::decode(None, r)
+                .map_err(|e| e.within(PathSegment::Index(idx)))
+        })
     }
 }
 
-impl DeltaEncodable for Arc<str> {
+#[cfg(feature = "alloc")]
+impl DeltaEncodable for String {
     #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
-        encode_str(self, base.map(|v| &**v), w)
+        w.write_str(self, base.map(|v| v.as_str()))
     }
 
     #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        decode_string(base.map(|v| &**v), r).map(|v| v.into())
+        r.read_string(base.map(|v| v.as_str()))
     }
 }
 
+macro_rules! impl_str {
+    ($($ty:ty,)*) => {
+    $(
+        #[cfg(feature = "alloc")]
+        impl DeltaEncodable for $ty {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                w.write_str(self, base.map(|v| &**v))
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                r.read_string(base.map(|v| &**v)).map(|v| v.into())
+            }
+        }
+    )*
+    };
+}
+
+impl_str!(Arc<str>, Box<str>, Cow<'static, str>,);
+
+#[cfg(feature = "alloc")]
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
 pub struct AlwaysVec<T>(pub Vec<T>);
 
+#[cfg(feature = "alloc")]
 impl <T> DeltaEncodable for AlwaysVec<T>
     where T: DeltaEncodable
 {
     #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
-        write_len_bits(w, self.0.len())?;
+        w.write_len(self.0.len())?;
         for (idx, val) in self.0.iter().enumerate() {
-            T::encode(val, base.and_then(|v | v.0.get(idx)), w)?;
+            T::encode(val, base.and_then(|v | v.0.get(idx)), w)
+                .map_err(|e| e.within(PathSegment::Index(idx)))?;
         }
         Ok(())
     }
 
     #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        let len = read_len_bits(r)?;
+        let len = r.read_len()?;
         let mut buf = Vec::with_capacity(len);
         for idx in 0 .. len {
-            buf.push(T::decode(base.and_then(|v| v.0.get(idx)), r)?);
+            buf.push(T::decode(base.and_then(|v| v.0.get(idx)), r)
+                .map_err(|e| e.within(PathSegment::Index(idx)))?);
         }
         Ok(AlwaysVec(buf))
     }
 }
 
-impl <T> DeltaEncodable for Vec<T>
-    where T: DeltaEncodable,
-          Vec<T>: PartialEq + Clone
+// Slices are encoded as a changed bit and the length followed by the
+// elements that differ from the element at the same index in the base.
+// Elements past the end of the base are sent in full.
+#[cfg(feature = "alloc")]
+fn encode_slice<T, W>(val: &[T], base: Option<&[T]>, w: &mut W) -> Result<(), DeltaError>
+    where T: DeltaEncodable + PartialEq,
+          W: BitWrite
 {
-    #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
-    {
-        if let Some(base) = base {
-            if base == self {
-                w.write_bool(false)?;
-                return Ok(())
-            }
-        }
-        w.write_bool(true)?;
+    if base == Some(val) {
+        return w.write_bool(false);
+    }
+    w.write_bool(true)?;
 
-        write_len_bits(w, self.len())?;
-        for (idx, val) in self.iter().enumerate() {
-            T::encode(val, base.and_then(|v | v.get(idx)), w)?;
-        }
-        Ok(())
+    w.write_len(val.len())?;
+    let base = base.unwrap_or(&[]);
+    let shared = val.len().min(base.len());
+    if shared > 0 {
+        write_changes(w, shared, |idx| val[idx] != base[idx], |w, idx| {
+            T::encode(&val[idx], Some(&base[idx]), w)
+                .map_err(|e| e.within(PathSegment::Index(idx)))
+        })?;
     }
+    for (idx, val) in val.iter().enumerate().skip(shared) {
+        T::encode(val, None, w)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+    }
+    Ok(())
+}
 
-    #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
-    {
-        if r.read_bool()? {
-            let len = read_len_bits(r)?;
-            let mut buf = Vec::with_capacity(len);
-            for idx in 0 .. len {
-                buf.push(T::decode(base.and_then(|v| v.get(idx)), r)?);
-            }
-            Ok(buf)
-        } else if let Some(base) = base {
-            Ok(base.to_owned())
-        } else {
-            Err(io::Error::new(io::ErrorKind::InvalidInput, "Missing previous vec state"))
-        }
+#[cfg(feature = "alloc")]
+fn decode_slice<T, R>(base: Option<&[T]>, r: &mut R) -> Result<Vec<T>, DeltaError>
+    where T: DeltaEncodable + Clone,
+          R: BitRead
+{
+    if !r.read_bool()? {
+        return base.map(|v| v.to_vec()).ok_or_else(DeltaError::missing_baseline);
+    }
+
+    let len = r.read_len()?;
+    let base = base.unwrap_or(&[]);
+    let shared = len.min(base.len());
+    let mut buf = Vec::with_capacity(len);
+    if shared > 0 {
+        read_changes(r, shared, |r, idx, changed| {
+            buf.push(if changed {
+                T::decode(Some(&base[idx]), r)
+                    .map_err(|e| e.within(PathSegment::Index(idx)))?
+            } else {
+                base[idx].clone()
+            });
+            Ok(())
+        })?;
     }
+    for idx in shared .. len {
+        buf.push(T::decode(None, r)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?);
+    }
+    Ok(buf)
 }
 
+macro_rules! impl_slice {
+    ($($ty:ty,)*) => {
+    $(
+        /// Only the elements that differ from the element at the same index
+        /// in the base are sent. Use `AlwaysVec` to always send every element
+        /// or `DiffVec` when elements are inserted and removed.
+        #[cfg(feature = "alloc")]
+        impl <T> DeltaEncodable for $ty
+            where T: DeltaEncodable + PartialEq + Clone
+        {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                encode_slice(self, base.map(|v| &v[..]), w)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                decode_slice(base.map(|v| &v[..]), r).map(|v| v.into())
+            }
+        }
+    )*
+    };
+}
+
+impl_slice!(Vec<T>, Box<[T]>, Arc<[T]>, Cow<'static, [T]>,);
+
 impl <T> DeltaEncodable for Option<T>
     where T: DeltaEncodable
 {
     #[inline]
-    fn encode<W>(&self, base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
         if let Some(ref s) = *self {
             w.write_bool(true)?;
@@ -188,8 +469,8 @@ impl <T> DeltaEncodable for Option<T>
     }
 
     #[inline]
-    fn decode<R>(base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
         if r.read_bool()? {
             Ok(Some(
@@ -202,20 +483,129 @@ impl <T> DeltaEncodable for Option<T>
 }
 
 
-impl DeltaEncodable for f32 {
+macro_rules! impl_prim {
+    ($($ty:ty => ($wide:ty, $bits:expr, $emethod:ident, $dmethod:ident),)*) => {
+    $(
+        impl DeltaEncodable for $ty {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if base == Some(self) {
+                    return w.write_bool(false);
+                }
+                w.write_bool(true)?;
+                w.$emethod(*self as $wide, $bits)
+            }
+
+            #[inline]
+            fn encode_changed<W>(&self, _base: &Self, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                w.$emethod(*self as $wide, $bits)
+            }
+
+            #[inline]
+            fn decode_changed<R>(_base: &Self, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                Ok(r.$dmethod($bits)? as $ty)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if r.read_bool()? {
+                    Ok(r.$dmethod($bits)? as $ty)
+                } else {
+                    base.cloned().ok_or_else(DeltaError::missing_baseline)
+                }
+            }
+        }
+    )*
+    };
+}
+
+// `usize` and `isize` are always sent as 64 bits so that the output
+// doesn't depend on the platform.
+impl_prim!(
+    i8 => (i64, 8, write_signed, read_signed),
+    i16 => (i64, 16, write_signed, read_signed),
+    i32 => (i64, 32, write_signed, read_signed),
+    i64 => (i64, 64, write_signed, read_signed),
+    isize => (i64, 64, write_signed, read_signed),
+    u8 => (u64, 8, write_unsigned, read_unsigned),
+    u16 => (u64, 16, write_unsigned, read_unsigned),
+    u32 => (u64, 32, write_unsigned, read_unsigned),
+    u64 => (u64, 64, write_unsigned, read_unsigned),
+    usize => (u64, 64, write_unsigned, read_unsigned),
+);
+
+/// Sent as a single bit, a changed bit wouldn't save anything
+impl DeltaEncodable for bool {
     #[inline]
-    fn encode<W>(&self, _base: Option<&Self>, w: &mut Writer<W>) -> io::Result<()>
-        where W: Write
+    fn encode<W>(&self, _base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
     {
-        w.write_f32(*self)
+        w.write_bool(*self)
     }
 
     #[inline]
-    fn decode<R>(_base: Option<&Self>, r: &mut Reader<R>) -> io::Result<Self>
-        where R: Read
+    fn decode<R>(_base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
     {
-        r.read_f32()
+        r.read_bool()
     }
 }
 
-impl_create_array!([32] a, b, c, d, e, f, g, h, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x, y, z, za, zb, zc, zd, ze, zf, zg,);
\ No newline at end of file
+macro_rules! impl_float {
+    ($($ty:ty => ($emethod:ident, $dmethod:ident),)*) => {
+    $(
+        /// Compared bitwise against the base so that `NaN` values are
+        /// only resent when they change.
+        impl DeltaEncodable for $ty {
+            #[inline]
+            fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                if base.is_some_and(|v| v.to_bits() == self.to_bits()) {
+                    return w.write_bool(false);
+                }
+                w.write_bool(true)?;
+                w.$emethod(*self)
+            }
+
+            #[inline]
+            fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                if r.read_bool()? {
+                    r.$dmethod()
+                } else {
+                    base.cloned().ok_or_else(DeltaError::missing_baseline)
+                }
+            }
+
+            #[inline]
+            fn encode_changed<W>(&self, _base: &Self, w: &mut W) -> Result<(), DeltaError>
+                where W: BitWrite
+            {
+                w.$emethod(*self)
+            }
+
+            #[inline]
+            fn decode_changed<R>(_base: &Self, r: &mut R) -> Result<Self, DeltaError>
+                where R: BitRead
+            {
+                r.$dmethod()
+            }
+        }
+    )*
+    };
+}
+
+impl_float!(
+    f32 => (write_f32, read_f32),
+    f64 => (write_f64, read_f64),
+);
@@ -0,0 +1,245 @@
+use super::*;
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(any(feature = "std", feature = "indexmap"))]
+use core::hash::{BuildHasher, Hash};
+
+/// Common interface over the supported map types.
+///
+/// `entries` must return the entries in an order that only depends on
+/// the contents of the map so that the output is deterministic.
+trait DeltaMap<K, V>: Clone + Default {
+    fn entries(&self) -> Vec<(&K, &V)>;
+    fn lookup(&self, key: &K) -> Option<&V>;
+    fn insert_entry(&mut self, key: K, val: V);
+    fn remove_entry(&mut self, key: &K);
+}
+
+// Maps are encoded as a changed bit followed by the keys removed since
+// the base, the entries whose value changed (as a delta against the
+// old value) and finally the newly inserted entries.
+fn encode_map<M, K, V, W>(map: &M, base: Option<&M>, w: &mut W) -> Result<(), DeltaError>
+    where M: DeltaMap<K, V>,
+          K: DeltaEncodable,
+          V: DeltaEncodable + PartialEq,
+          W: BitWrite
+{
+    let mut removed = vec![];
+    let mut changed = vec![];
+    let mut inserted = vec![];
+    if let Some(base) = base {
+        for (key, _) in base.entries() {
+            if map.lookup(key).is_none() {
+                removed.push(key);
+            }
+        }
+        for (key, val) in map.entries() {
+            match base.lookup(key) {
+                Some(old) if old == val => {},
+                Some(old) => changed.push((key, val, old)),
+                None => inserted.push((key, val)),
+            }
+        }
+        if removed.is_empty() && changed.is_empty() && inserted.is_empty() {
+            w.write_bool(false)?;
+            return Ok(());
+        }
+    } else {
+        inserted = map.entries();
+    }
+    w.write_bool(true)?;
+
+    w.write_len(removed.len())?;
+    for (idx, key) in removed.into_iter().enumerate() {
+        K::encode(key, None, w)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+    }
+    w.write_len(changed.len())?;
+    for (idx, (key, val, old)) in changed.into_iter().enumerate() {
+        K::encode(key, None, w)
+            .and_then(|_| V::encode(val, Some(old), w))
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+    }
+    w.write_len(inserted.len())?;
+    for (idx, (key, val)) in inserted.into_iter().enumerate() {
+        K::encode(key, None, w)
+            .and_then(|_| V::encode(val, None, w))
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+    }
+    Ok(())
+}
+
+fn decode_map<M, K, V, R>(base: Option<&M>, r: &mut R) -> Result<M, DeltaError>
+    where M: DeltaMap<K, V>,
+          K: DeltaEncodable,
+          V: DeltaEncodable,
+          R: BitRead
+{
+    if !r.read_bool()? {
+        return base.cloned().ok_or_else(DeltaError::missing_baseline);
+    }
+    let mut map = base.cloned().unwrap_or_default();
+
+    let removed = r.read_len()?;
+    for idx in 0 .. removed {
+        let key = K::decode(None, r)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        map.remove_entry(&key);
+    }
+    let changed = r.read_len()?;
+    for idx in 0 .. changed {
+        let (key, val) = (|| {
+            let key = K::decode(None, r)?;
+            let old = base.and_then(|v| v.lookup(&key))
+                .ok_or_else(DeltaError::missing_baseline)?;
+            let val = V::decode(Some(old), r)?;
+            Ok((key, val))
+        })().map_err(|e: DeltaError| e.within(PathSegment::Index(idx)))?;
+        map.insert_entry(key, val);
+    }
+    let inserted = r.read_len()?;
+    for idx in 0 .. inserted {
+        let key = K::decode(None, r)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        let val = V::decode(None, r)
+            .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        map.insert_entry(key, val);
+    }
+    Ok(map)
+}
+
+impl <K, V> DeltaMap<K, V> for BTreeMap<K, V>
+    where K: Ord + Clone,
+          V: Clone
+{
+    fn entries(&self) -> Vec<(&K, &V)> {
+        self.iter().collect()
+    }
+
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert_entry(&mut self, key: K, val: V) {
+        self.insert(key, val);
+    }
+
+    fn remove_entry(&mut self, key: &K) {
+        self.remove(key);
+    }
+}
+
+impl <K, V> DeltaEncodable for BTreeMap<K, V>
+    where K: DeltaEncodable + Ord + Clone,
+          V: DeltaEncodable + PartialEq + Clone
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        encode_map(self, base, w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        decode_map(base, r)
+    }
+}
+
+/// Entries are sorted by key so that the output doesn't depend
+/// on the hasher.
+#[cfg(feature = "std")]
+impl <K, V, S> DeltaMap<K, V> for HashMap<K, V, S>
+    where K: Ord + Hash + Clone,
+          V: Clone,
+          S: BuildHasher + Default + Clone
+{
+    fn entries(&self) -> Vec<(&K, &V)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert_entry(&mut self, key: K, val: V) {
+        self.insert(key, val);
+    }
+
+    fn remove_entry(&mut self, key: &K) {
+        self.remove(key);
+    }
+}
+
+#[cfg(feature = "std")]
+impl <K, V, S> DeltaEncodable for HashMap<K, V, S>
+    where K: DeltaEncodable + Ord + Hash + Clone,
+          V: DeltaEncodable + PartialEq + Clone,
+          S: BuildHasher + Default + Clone
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        encode_map(self, base, w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        decode_map(base, r)
+    }
+}
+
+/// Entries are sent in index order. Removed entries are shifted out and
+/// new entries are appended so the decoded map keeps the order of the
+/// base for existing keys.
+#[cfg(feature = "indexmap")]
+impl <K, V, S> DeltaMap<K, V> for indexmap::IndexMap<K, V, S>
+    where K: Hash + Eq + Clone,
+          V: Clone,
+          S: BuildHasher + Default + Clone
+{
+    fn entries(&self) -> Vec<(&K, &V)> {
+        self.iter().collect()
+    }
+
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert_entry(&mut self, key: K, val: V) {
+        self.insert(key, val);
+    }
+
+    fn remove_entry(&mut self, key: &K) {
+        self.shift_remove(key);
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl <K, V, S> DeltaEncodable for indexmap::IndexMap<K, V, S>
+    where K: DeltaEncodable + Hash + Eq + Clone,
+          V: DeltaEncodable + PartialEq + Clone,
+          S: BuildHasher + Default + Clone
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        encode_map(self, base, w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        decode_map(base, r)
+    }
+}
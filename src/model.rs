@@ -0,0 +1,23 @@
+/// Fixed probabilities for the bits written within a `#[delta_model]`
+/// field or type.
+///
+/// This is a table of per-bit probabilities looked up by context hash for
+/// the binary range coder, not a Huffman or ANS symbol table. Only writers
+/// that model their input use the probabilities, the others ignore which
+/// model is in use.
+#[cfg_attr(feature = "range_coder", doc = "")]
+#[cfg_attr(feature = "range_coder", doc = "The tables are produced by `ModelTrainer` from recorded traffic and")]
+#[cfg_attr(feature = "range_coder", doc = "used by `RangeWriter` and `RangeReader`.")]
+#[derive(Debug)]
+pub struct StaticModel {
+    /// The probability out of 2048 that each bit is a zero, indexed by the
+    /// low bits of the bit's context hash. The length must be a power of
+    /// two.
+    pub probs: &'static [u16],
+}
+
+impl StaticModel {
+    /// A model where every bit is equally likely, for use as a
+    /// placeholder until a model has been trained
+    pub const UNTRAINED: StaticModel = StaticModel { probs: &[1024] };
+}
@@ -0,0 +1,216 @@
+use super::*;
+use nalgebra::{
+    ArrayStorage, Isometry3, OPoint, Quaternion, RealField, SMatrix, Scalar,
+    Similarity3, Translation, Unit, UnitQuaternion,
+};
+
+/// Against a base a single bit is sent when the matrix is unchanged,
+/// otherwise each component is sent with its own change bit. This covers
+/// `SVector` as well.
+impl <T, const R: usize, const C: usize> DeltaEncodable for SMatrix<T, R, C>
+    where T: Scalar + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        if let Some(base) = base {
+            if base == self {
+                return w.write_bool(false);
+            }
+            w.write_bool(true)?;
+        }
+        // Components are visited in column-major order, matching the
+        // linear index of the matrix
+        for (idx, val) in self.iter().enumerate() {
+            T::encode(val, base.map(|v| &v[idx]), w)
+                .map_err(|e| e.within(PathSegment::Index(idx)))?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn decode<Rd>(base: Option<&Self>, r: &mut Rd) -> Result<Self, DeltaError>
+        where Rd: BitRead
+    {
+        if let Some(base) = base {
+            if !r.read_bool()? {
+                return Ok(base.clone());
+            }
+        }
+        let data = <[[T; R]; C]>::create(|col| {
+            <[T; R]>::create(|row| {
+                let idx = col * R + row;
+                T::decode(base.map(|v| &v[idx]), r)
+                    .map_err(|e| e.within(PathSegment::Index(idx)))
+            })
+        })?;
+        Ok(SMatrix::from_array_storage(ArrayStorage(data)))
+    }
+}
+
+impl <T, const D: usize> DeltaEncodable for OPoint<T, nalgebra::Const<D>>
+    where T: Scalar + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.coords.encode(base.map(|v| &v.coords), w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        Ok(OPoint::from(SMatrix::decode(base.map(|v| &v.coords), r)?))
+    }
+}
+
+impl <T, const D: usize> DeltaEncodable for Translation<T, D>
+    where T: Scalar + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.vector.encode(base.map(|v| &v.vector), w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        Ok(Translation::from(SMatrix::decode(base.map(|v| &v.vector), r)?))
+    }
+}
+
+/// Sent as the `i`, `j`, `k`, `w` coordinates
+impl <T> DeltaEncodable for Quaternion<T>
+    where T: Scalar + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.coords.encode(base.map(|v| &v.coords), w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        Ok(Quaternion::from(SMatrix::decode(base.map(|v| &v.coords), r)?))
+    }
+}
+
+impl <T> QuatComponents for UnitQuaternion<T>
+    where T: RealField
+{
+    #[inline]
+    fn to_xyzw(&self) -> [f64; 4] {
+        let c = &self.as_ref().coords;
+        [c.x.clone(), c.y.clone(), c.z.clone(), c.w.clone()]
+            .map(|v| v.to_subset().unwrap_or(0.0))
+    }
+
+    #[inline]
+    fn from_xyzw(xyzw: [f64; 4]) -> Self {
+        let [x, y, z, w] = xyzw.map(|v| T::from_subset(&v));
+        Unit::new_normalize(Quaternion::new(w, x, y, z))
+    }
+}
+
+/// Sent as a normalized rotation using `encode_quat` with
+/// `DEFAULT_QUAT_BITS` bits per component
+impl <T> DeltaEncodable for UnitQuaternion<T>
+    where T: RealField
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        encode_quat(self, base, DEFAULT_QUAT_BITS, w)
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        decode_quat(base, DEFAULT_QUAT_BITS, r)
+    }
+}
+
+impl <T> DeltaEncodable for Isometry3<T>
+    where T: RealField + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.translation.encode(base.map(|v| &v.translation), w)
+            .map_err(|e| e.within(PathSegment::Field("translation")))?;
+        self.rotation.encode(base.map(|v| &v.rotation), w)
+            .map_err(|e| e.within(PathSegment::Field("rotation")))
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        Ok(Isometry3 {
+            translation: Translation::decode(base.map(|v| &v.translation), r)
+                .map_err(|e| e.within(PathSegment::Field("translation")))?,
+            rotation: UnitQuaternion::decode(base.map(|v| &v.rotation), r)
+                .map_err(|e| e.within(PathSegment::Field("rotation")))?,
+        })
+    }
+}
+
+/// Fails to decode with `DeltaError::InvalidData` if the scaling is zero
+impl <T> DeltaEncodable for Similarity3<T>
+    where T: RealField + DeltaEncodable
+{
+    #[inline]
+    fn encode<W>(&self, base: Option<&Self>, w: &mut W) -> Result<(), DeltaError>
+        where W: BitWrite
+    {
+        self.isometry.encode(base.map(|v| &v.isometry), w)
+            .map_err(|e| e.within(PathSegment::Field("isometry")))?;
+        let base_scaling = base.map(|v| v.scaling());
+        self.scaling().encode(base_scaling.as_ref(), w)
+            .map_err(|e| e.within(PathSegment::Field("scaling")))
+    }
+
+    #[inline]
+    fn decode<R>(base: Option<&Self>, r: &mut R) -> Result<Self, DeltaError>
+        where R: BitRead
+    {
+        let isometry = Isometry3::decode(base.map(|v| &v.isometry), r)
+            .map_err(|e| e.within(PathSegment::Field("isometry")))?;
+        let base_scaling = base.map(|v| v.scaling());
+        let scaling = T::decode(base_scaling.as_ref(), r)
+            .map_err(|e| e.within(PathSegment::Field("scaling")))?;
+        if scaling.is_zero() {
+            return Err(DeltaError::invalid_data("zero scaling")
+                .within(PathSegment::Field("scaling")));
+        }
+        Ok(Similarity3::from_isometry(isometry, scaling))
+    }
+}
+
+impl <T> Direction3 for nalgebra::Vector3<T>
+    where T: RealField
+{
+    #[inline]
+    fn to_xyz(&self) -> [f64; 3] {
+        [self.x.clone(), self.y.clone(), self.z.clone()]
+            .map(|v| v.to_subset().unwrap_or(0.0))
+    }
+
+    #[inline]
+    fn from_xyz(xyz: [f64; 3]) -> Self {
+        let [x, y, z] = xyz.map(|v| T::from_subset(&v));
+        nalgebra::Vector3::new(x, y, z)
+    }
+}
@@ -0,0 +1,90 @@
+/// Returns the number of bits needed to cover `min..max` in steps of
+/// at most `precision`.
+///
+/// This is a `const fn` so that `#[delta_precision]` fields can have it
+/// evaluated at compile time.
+pub const fn range_bits(min: f64, max: f64, precision: f64) -> u8 {
+    assert!(max > min, "Range must not be empty");
+    assert!(precision > 0.0, "Precision must be positive");
+    let steps = (max - min) / precision;
+    let mut bits = 1;
+    while bits < 64 && ((1u64 << bits) - 1) as f64 + 1e-9 < steps {
+        bits += 1;
+    }
+    bits
+}
+
+/// Maps `val` onto one of the `2^bits` evenly spaced values covering
+/// `min..=max`, clamping values outside of the range. `NaN` maps to `min`.
+#[inline]
+pub fn quantize(val: f64, min: f64, max: f64, bits: u8) -> u64 {
+    let steps = steps(bits);
+    let val = if val >= min { val } else { min };
+    let val = if val <= max { val } else { max };
+    // The value is positive so adding a half rounds to nearest
+    let q = ((val - min) / (max - min) * steps as f64 + 0.5) as u64;
+    q.min(steps)
+}
+
+/// Reverses `quantize`, both sides reconstruct exactly the same value
+#[inline]
+pub fn dequantize(q: u64, min: f64, max: f64, bits: u8) -> f64 {
+    let steps = steps(bits);
+    if q >= steps {
+        return max;
+    }
+    min + (max - min) * (q as f64 / steps as f64)
+}
+
+/// Maps `val` onto `bits` bit signed steps covering `-max..=max` so that
+/// zero and both ends of the range are exact, clamping values outside of
+/// the range. `NaN` maps to zero.
+#[inline]
+pub fn quantize_signed(val: f64, max: f64, bits: u8) -> i64 {
+    let half = half_steps(bits);
+    let val = if val >= -max { val } else { -max };
+    let val = if val <= max { val } else { max };
+    let scaled = val / max * half as f64;
+    if scaled >= 0.0 {
+        (scaled + 0.5) as i64
+    } else {
+        -((-scaled + 0.5) as i64)
+    }
+}
+
+/// Reverses `quantize_signed`
+#[inline]
+pub fn dequantize_signed(q: i64, max: f64, bits: u8) -> f64 {
+    let half = half_steps(bits);
+    q.clamp(-half, half) as f64 / half as f64 * max
+}
+
+#[inline]
+fn half_steps(bits: u8) -> i64 {
+    (1i64 << (bits.clamp(2, 63) - 1)) - 1
+}
+
+#[inline]
+fn steps(bits: u8) -> u64 {
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+/// Newton's method as `f64::sqrt` needs `std`
+#[cfg(not(feature = "std"))]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    if !(x > 0.0 && x.is_finite()) {
+        return if x == 0.0 || x == f64::INFINITY { x } else { f64::NAN };
+    }
+    // Halving the exponent gives a guess within a factor of two
+    let mut guess = f64::from_bits((x.to_bits() >> 1) + (1023 << 51));
+    for _ in 0 .. 6 {
+        guess = 0.5 * (guess + x / guess);
+    }
+    guess
+}
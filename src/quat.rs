@@ -0,0 +1,100 @@
+use super::*;
+use quantize::sqrt;
+
+/// The number of bits per component used by the quaternion impls of
+/// the math library integrations.
+pub const DEFAULT_QUAT_BITS: u8 = 15;
+
+/// The largest magnitude the smallest three components of a unit
+/// quaternion can have
+const MAX_COMPONENT: f64 = core::f64::consts::FRAC_1_SQRT_2;
+
+/// A quaternion type that can be sent with `encode_quat`
+pub trait QuatComponents: Sized {
+    /// Returns the components in `x, y, z, w` order
+    fn to_xyzw(&self) -> [f64; 4];
+
+    /// Creates the quaternion from components in `x, y, z, w` order
+    fn from_xyzw(xyzw: [f64; 4]) -> Self;
+}
+
+/// Normalizes the quaternion and returns the index of its largest
+/// component along with the other three quantized.
+fn pack(q: [f64; 4], bits: u8) -> (u64, [i64; 3]) {
+    let len = sqrt(q.iter().map(|v| v * v).sum());
+    let q = if len > 0.0 && len.is_finite() {
+        q.map(|v| v / len)
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    };
+    let mut largest = 0;
+    for idx in 1 .. 4 {
+        if q[idx].abs() > q[largest].abs() {
+            largest = idx;
+        }
+    }
+    // `q` and `-q` are the same rotation so the largest component can
+    // always be made positive
+    let sign = if q[largest] < 0.0 { -1.0 } else { 1.0 };
+    let mut packed = [0; 3];
+    for (out, idx) in packed.iter_mut().zip((0 .. 4).filter(|&v| v != largest)) {
+        *out = quantize_signed(q[idx] * sign, MAX_COMPONENT, bits);
+    }
+    (largest as u64, packed)
+}
+
+fn unpack(largest: usize, packed: [i64; 3], bits: u8) -> [f64; 4] {
+    let mut q = [0.0; 4];
+    let mut sum = 0.0;
+    for (&val, idx) in packed.iter().zip((0 .. 4).filter(|&v| v != largest)) {
+        q[idx] = dequantize_signed(val, MAX_COMPONENT, bits);
+        sum += q[idx] * q[idx];
+    }
+    q[largest] = sqrt((1.0 - sum).max(0.0));
+    q
+}
+
+/// Writes a rotation using the smallest three encoding: the index of the
+/// largest component followed by the other three quantized to `bits`
+/// bits each, which must be at least 2 so that a component keeps its sign.
+///
+/// The quaternion is normalized first. Against a base a single bit is
+/// sent if both quantize to the same value.
+pub fn encode_quat<Q, W>(val: &Q, base: Option<&Q>, bits: u8, w: &mut W) -> Result<(), DeltaError>
+    where Q: QuatComponents,
+          W: BitWrite
+{
+    debug_assert!(bits >= 2, "Quaternion components need at least 2 bits");
+    let (largest, packed) = pack(val.to_xyzw(), bits);
+    if let Some(base) = base {
+        if pack(base.to_xyzw(), bits) == (largest, packed) {
+            return w.write_bool(false);
+        }
+        w.write_bool(true)?;
+    }
+    w.write_unsigned(largest, 2)?;
+    for val in packed {
+        w.write_signed(val, bits)?;
+    }
+    Ok(())
+}
+
+/// Reads a rotation written by `encode_quat`
+pub fn decode_quat<Q, R>(base: Option<&Q>, bits: u8, r: &mut R) -> Result<Q, DeltaError>
+    where Q: QuatComponents + Clone,
+          R: BitRead
+{
+    debug_assert!(bits >= 2, "Quaternion components need at least 2 bits");
+    if let Some(base) = base {
+        if !r.read_bool()? {
+            return Ok(base.clone());
+        }
+    }
+    let largest = r.read_unsigned(2)? as usize;
+    let packed = [
+        r.read_signed(bits)?,
+        r.read_signed(bits)?,
+        r.read_signed(bits)?,
+    ];
+    Ok(Q::from_xyzw(unpack(largest, packed, bits)))
+}
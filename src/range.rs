@@ -0,0 +1,556 @@
+use super::*;
+
+/// `log2` of the number of probabilities kept by `RangeWriter` and
+/// `RangeReader` unless specified otherwise
+pub const DEFAULT_CONTEXT_BITS: u8 = 16;
+
+/// Probabilities are fixed point numbers out of `1 << PROB_BITS`
+const PROB_BITS: u32 = 11;
+const PROB_ONE: u16 = 1 << PROB_BITS;
+/// Trained probabilities are kept at least this far from certain so a
+/// bit that wasn't seen in training can still be coded
+const MIN_PROB: u16 = 16;
+/// How quickly probabilities adapt, larger is slower
+const ADAPT_SHIFT: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+/// The number of leading bits of a value that are modelled as a tree
+/// so that common small values are learnt as a whole, the remaining
+/// bits are only modelled by their position.
+const TREE_BITS: u8 = 8;
+/// Writes beyond this many within a field share their statistics
+const MAX_STEP: u32 = 15;
+
+#[derive(Clone, Copy)]
+struct Context {
+    hash: u32,
+    /// The number of values written so far within the field
+    step: u32,
+}
+
+const ROOT: Context = Context { hash: 0, step: 0 };
+
+#[inline]
+fn mix(hash: u32, val: u32) -> u32 {
+    let hash = (hash ^ val).wrapping_mul(0x9E37_79B1);
+    hash ^ (hash >> 15)
+}
+
+/// Tracks the field being written so that each bit can be given a hash
+/// of the field, the position within the field and the bits of the
+/// value seen so far.
+struct Contexts {
+    /// The context of each field entered, the top is the current field
+    stack: Vec<Context>,
+    /// The static models entered along with the stack from outside them,
+    /// fields within a model are hashed relative to where it was entered
+    models: Vec<(&'static StaticModel, Vec<Context>)>,
+}
+
+impl Contexts {
+    fn new() -> Contexts {
+        Contexts {
+            stack: vec![ROOT],
+            models: Vec::new(),
+        }
+    }
+
+    fn enter(&mut self, id: u32) {
+        let parent = *self.stack.last().expect("context stack is never empty");
+        self.stack.push(Context {
+            hash: mix(parent.hash, id),
+            step: 0,
+        });
+    }
+
+    fn leave(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    fn enter_model(&mut self, model: &'static StaticModel) {
+        let outer = core::mem::replace(&mut self.stack, vec![ROOT]);
+        self.models.push((model, outer));
+    }
+
+    fn leave_model(&mut self) {
+        if let Some((_, outer)) = self.models.pop() {
+            self.stack = outer;
+        }
+    }
+
+    /// The static model in use, if any
+    fn model(&self) -> Option<&'static StaticModel> {
+        self.models.last().map(|v| v.0)
+    }
+
+    /// Drops any fields or models left entered, e.g. by a failed encode
+    fn reset(&mut self) {
+        self.models.clear();
+        self.stack.clear();
+        self.stack.push(ROOT);
+    }
+
+    /// Returns the hash for the next value of `bits` bits written in the
+    /// current field
+    fn next_value(&mut self, bits: u8) -> u32 {
+        let context = self.stack.last_mut().expect("context stack is never empty");
+        let hash = mix(mix(context.hash, context.step), u32::from(bits));
+        context.step = (context.step + 1).min(MAX_STEP);
+        hash
+    }
+}
+
+/// Returns the hash for bit `idx` (counting from the most significant)
+/// of a value given the bits before it
+#[inline]
+fn bit_hash(value: u32, idx: u8, prefix: u64) -> u32 {
+    let node = if idx < TREE_BITS {
+        // The leading one keeps prefixes of different lengths apart
+        (1 << idx) | prefix as u32
+    } else {
+        0x1_0000 | u32::from(idx)
+    };
+    mix(value, node)
+}
+
+/// Adaptive probabilities looked up by the hash of each bit, unless a
+/// static model has been entered in which case its fixed probabilities
+/// are used instead.
+struct Model {
+    contexts: Contexts,
+    probs: Vec<u16>,
+    mask: u32,
+}
+
+impl Model {
+    fn new(bits: u8) -> Model {
+        assert!(bits > 0 && bits <= 24, "Context bits must be between 1 and 24");
+        Model {
+            contexts: Contexts::new(),
+            probs: vec![PROB_ONE / 2; 1 << bits],
+            mask: (1 << bits) - 1,
+        }
+    }
+
+    #[inline]
+    fn prob(&self, hash: u32) -> u32 {
+        match self.contexts.model() {
+            Some(model) => {
+                let idx = hash as usize & model.probs.len().wrapping_sub(1);
+                model.probs.get(idx)
+                    .map_or(u32::from(PROB_ONE / 2), |&v| u32::from(v).clamp(1, u32::from(PROB_ONE) - 1))
+            },
+            None => u32::from(self.probs[(hash & self.mask) as usize]),
+        }
+    }
+
+    #[inline]
+    fn update(&mut self, hash: u32, bit: bool) {
+        if self.contexts.model().is_some() {
+            return;
+        }
+        let prob = &mut self.probs[(hash & self.mask) as usize];
+        if bit {
+            *prob -= *prob >> ADAPT_SHIFT;
+        } else {
+            *prob += (PROB_ONE - *prob) >> ADAPT_SHIFT;
+        }
+    }
+}
+
+/// A `BitWrite` that compresses its input with an adaptive binary range
+/// coder.
+///
+/// Every bit is coded with a probability learnt from the bits previously
+/// written in the same field (as reported through `enter_context`), so
+/// change bits that are rarely set and deltas that are usually small
+/// cost much less than a bit each.
+///
+/// The learnt probabilities are kept between blocks taken with
+/// `take_block`, so a long stream compresses better than each block
+/// would on its own. The matching `RangeReader` must be given the same
+/// blocks in the same order.
+pub struct RangeWriter {
+    model: Model,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+    buf: Vec<u8>,
+}
+
+impl RangeWriter {
+    pub fn new() -> RangeWriter {
+        RangeWriter::with_context_bits(DEFAULT_CONTEXT_BITS)
+    }
+
+    /// Creates a writer keeping `1 << bits` probabilities. The reader
+    /// must use the same number.
+    pub fn with_context_bits(bits: u8) -> RangeWriter {
+        RangeWriter {
+            model: Model::new(bits),
+            low: 0,
+            range: !0,
+            cache: 0,
+            cache_size: 1,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Finishes the current block and returns its bytes, keeping the
+    /// learnt probabilities for the next block
+    pub fn take_block(&mut self) -> Vec<u8> {
+        // Any value within the final range decodes the same, so pick the
+        // one ending in the most zero bytes and leave them for the reader
+        // to pad back in
+        let mut trailing = 4;
+        loop {
+            let mask = (1u64 << (8 * trailing)) - 1;
+            let val = (self.low + mask) & !mask;
+            if val < self.low + u64::from(self.range) {
+                self.low = val;
+                break;
+            }
+            trailing -= 1;
+        }
+        for _ in 0 .. 5 {
+            self.shift_low();
+        }
+        // The first byte is always zero as the coded value is below one
+        debug_assert_eq!(self.buf[0], 0);
+        self.buf.remove(0);
+        for _ in 0 .. trailing {
+            if self.buf.last() != Some(&0) {
+                break;
+            }
+            self.buf.pop();
+        }
+        self.low = 0;
+        self.range = !0;
+        self.cache = 0;
+        self.cache_size = 1;
+        self.model.contexts.reset();
+        core::mem::take(&mut self.buf)
+    }
+
+    /// Finishes the output and returns it
+    pub fn finish(mut self) -> Vec<u8> {
+        self.take_block()
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low >= 1 << 32 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            loop {
+                self.buf.push(byte.wrapping_add(carry));
+                byte = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    fn encode_bit(&mut self, hash: u32, bit: bool) {
+        let bound = (self.range >> PROB_BITS) * self.model.prob(hash);
+        if bit {
+            self.low += u64::from(bound);
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        self.model.update(hash, bit);
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+}
+
+impl Default for RangeWriter {
+    fn default() -> RangeWriter {
+        RangeWriter::new()
+    }
+}
+
+impl BitWrite for RangeWriter {
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> {
+        self.write_unsigned(u64::from(val), 1)
+    }
+
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> {
+        let value = self.model.contexts.next_value(bits);
+        let mut prefix = 0;
+        for idx in 0 .. bits {
+            let bit = (val >> (bits - idx - 1)) & 1 == 1;
+            self.encode_bit(bit_hash(value, idx, prefix), bit);
+            prefix = (prefix << 1) | u64::from(bit);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn enter_context(&mut self, id: u32) {
+        self.model.contexts.enter(id);
+    }
+
+    #[inline]
+    fn leave_context(&mut self) {
+        self.model.contexts.leave();
+    }
+
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) {
+        self.model.contexts.enter_model(model);
+    }
+
+    #[inline]
+    fn leave_model(&mut self) {
+        self.model.contexts.leave_model();
+    }
+}
+
+/// Reads blocks written by `RangeWriter`.
+pub struct RangeReader<'a> {
+    model: Model,
+    buf: &'a [u8],
+    pos: usize,
+    /// The number of zero bytes the writer trimmed from the end of the
+    /// block that have been read back
+    padding: u8,
+    range: u32,
+    code: u32,
+}
+
+impl <'a> RangeReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Result<RangeReader<'a>, DeltaError> {
+        RangeReader::with_context_bits(buf, DEFAULT_CONTEXT_BITS)
+    }
+
+    /// Creates a reader keeping `1 << bits` probabilities, this must
+    /// match the writer.
+    pub fn with_context_bits(buf: &'a [u8], bits: u8) -> Result<RangeReader<'a>, DeltaError> {
+        let mut reader = RangeReader {
+            model: Model::new(bits),
+            buf: &[],
+            pos: 0,
+            padding: 0,
+            range: !0,
+            code: 0,
+        };
+        reader.next_block(buf)?;
+        Ok(reader)
+    }
+
+    /// Starts reading the next block returned by `RangeWriter::take_block`
+    pub fn next_block(&mut self, buf: &'a [u8]) -> Result<(), DeltaError> {
+        self.buf = buf;
+        self.pos = 0;
+        self.padding = 0;
+        self.range = !0;
+        self.code = 0;
+        self.model.contexts.reset();
+        for _ in 0 .. 4 {
+            self.code = (self.code << 8) | u32::from(self.next_byte()?);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn next_byte(&mut self) -> Result<u8, DeltaError> {
+        if let Some(&byte) = self.buf.get(self.pos) {
+            self.pos += 1;
+            Ok(byte)
+        } else if self.padding < 4 {
+            self.padding += 1;
+            Ok(0)
+        } else {
+            Err(DeltaError::truncated())
+        }
+    }
+
+    fn decode_bit(&mut self, hash: u32) -> Result<bool, DeltaError> {
+        let bound = (self.range >> PROB_BITS) * self.model.prob(hash);
+        let bit = self.code >= bound;
+        if bit {
+            self.code -= bound;
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        self.model.update(hash, bit);
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.next_byte()?);
+        }
+        Ok(bit)
+    }
+}
+
+impl BitRead for RangeReader<'_> {
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, DeltaError> {
+        Ok(self.read_unsigned(1)? == 1)
+    }
+
+    #[inline]
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> {
+        let value = self.model.contexts.next_value(bits);
+        let mut prefix = 0;
+        for idx in 0 .. bits {
+            let bit = self.decode_bit(bit_hash(value, idx, prefix))?;
+            prefix = (prefix << 1) | u64::from(bit);
+        }
+        Ok(prefix)
+    }
+
+    #[inline]
+    fn enter_context(&mut self, id: u32) {
+        self.model.contexts.enter(id);
+    }
+
+    #[inline]
+    fn leave_context(&mut self) {
+        self.model.contexts.leave();
+    }
+
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) {
+        self.model.contexts.enter_model(model);
+    }
+
+    #[inline]
+    fn leave_model(&mut self) {
+        self.model.contexts.leave_model();
+    }
+}
+
+/// A `BitWrite` that discards its input and instead counts how often each
+/// bit is set in each context, to produce tables for `StaticModel`.
+///
+/// The result is a probability per bit context for the range coder rather
+/// than a Huffman or ANS table, so the coder still does its arithmetic per
+/// bit but skips the adaptive updates.
+///
+/// Encode recorded traffic with the trainer in place of a `RangeWriter`
+/// and write the result of `to_module` out as a Rust module.
+pub struct ModelTrainer {
+    contexts: Contexts,
+    /// The number of zeros and ones seen for each hash
+    counts: Vec<[u32; 2]>,
+    mask: u32,
+    only: Option<&'static StaticModel>,
+}
+
+impl ModelTrainer {
+    /// Creates a trainer producing a table of `1 << bits` probabilities
+    pub fn new(bits: u8) -> ModelTrainer {
+        assert!(bits <= 24, "Model bits must be at most 24");
+        ModelTrainer {
+            contexts: Contexts::new(),
+            counts: vec![[0; 2]; 1 << bits],
+            mask: (1 << bits) - 1,
+            only: None,
+        }
+    }
+
+    /// Only counts the bits written while `model` is entered, e.g. by
+    /// the fields marked with `#[delta_model]` that use it. Otherwise
+    /// every bit is counted.
+    pub fn only(mut self, model: &'static StaticModel) -> ModelTrainer {
+        self.only = Some(model);
+        self
+    }
+
+    /// Returns the trained probability of a zero bit for each hash
+    pub fn probs(&self) -> Vec<u16> {
+        self.counts.iter()
+            .map(|&[zeros, ones]| {
+                // Add one to each side so unseen contexts stay even
+                let (zeros, ones) = (u64::from(zeros) + 1, u64::from(ones) + 1);
+                let prob = (zeros << PROB_BITS) / (zeros + ones);
+                prob.clamp(u64::from(MIN_PROB), u64::from(PROB_ONE - MIN_PROB)) as u16
+            })
+            .collect()
+    }
+
+    /// Returns the source of a Rust module declaring the trained model as
+    /// `pub static #name: StaticModel`
+    pub fn to_module(&self, name: &str) -> String {
+        use core::fmt::Write;
+        let mut out = String::new();
+        let _ = writeln!(out, "// Generated by delta_encode::ModelTrainer");
+        let _ = writeln!(out, "pub static {}: delta_encode::StaticModel = delta_encode::StaticModel {{", name);
+        let _ = writeln!(out, "    probs: &[");
+        for line in self.probs().chunks(16) {
+            out.push_str("       ");
+            for prob in line {
+                let _ = write!(out, " {},", prob);
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(out, "    ],");
+        let _ = writeln!(out, "}};");
+        out
+    }
+
+    fn recording(&self) -> bool {
+        match self.only {
+            Some(only) => self.contexts.model().is_some_and(|v| core::ptr::eq(v, only)),
+            None => true,
+        }
+    }
+}
+
+impl BitWrite for ModelTrainer {
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> {
+        self.write_unsigned(u64::from(val), 1)
+    }
+
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> {
+        let value = self.contexts.next_value(bits);
+        if !self.recording() {
+            return Ok(());
+        }
+        let mut prefix = 0;
+        for idx in 0 .. bits {
+            let bit = (val >> (bits - idx - 1)) & 1 == 1;
+            let hash = bit_hash(value, idx, prefix);
+            let count = &mut self.counts[(hash & self.mask) as usize][usize::from(bit)];
+            *count = count.saturating_add(1);
+            prefix = (prefix << 1) | u64::from(bit);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn enter_context(&mut self, id: u32) {
+        self.contexts.enter(id);
+    }
+
+    #[inline]
+    fn leave_context(&mut self) {
+        self.contexts.leave();
+    }
+
+    #[inline]
+    fn enter_model(&mut self, model: &'static StaticModel) {
+        self.contexts.enter_model(model);
+    }
+
+    #[inline]
+    fn leave_model(&mut self) {
+        self.contexts.leave_model();
+    }
+}
@@ -0,0 +1,94 @@
+use super::*;
+
+/// Writes bits into a fixed size buffer, most significant bit first.
+///
+/// Fails with `DeltaError::Truncated` once the buffer is full.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl <'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter {
+            buf,
+            pos: 0,
+        }
+    }
+
+    /// The number of bits written so far
+    pub fn bit_len(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the part of the buffer that was written to, the
+    /// unused bits of the final byte are zero.
+    pub fn finish(self) -> &'a mut [u8] {
+        let len = self.pos.div_ceil(8);
+        &mut self.buf[.. len]
+    }
+}
+
+impl BitWrite for SliceWriter<'_> {
+    #[inline]
+    fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> {
+        let byte = self.buf.get_mut(self.pos / 8)
+            .ok_or_else(DeltaError::truncated)?;
+        let bit = 0x80 >> (self.pos % 8);
+        if val {
+            *byte |= bit;
+        } else {
+            *byte &= !bit;
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> {
+        for i in (0 .. bits).rev() {
+            self.write_bool((val >> i) & 1 == 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads bits from a buffer written by `SliceWriter`.
+pub struct SliceReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl <'a> SliceReader<'a> {
+    pub fn new(buf: &'a [u8]) -> SliceReader<'a> {
+        SliceReader {
+            buf,
+            pos: 0,
+        }
+    }
+
+    /// The number of bits read so far
+    pub fn bit_len(&self) -> usize {
+        self.pos
+    }
+}
+
+impl BitRead for SliceReader<'_> {
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, DeltaError> {
+        let byte = self.buf.get(self.pos / 8)
+            .ok_or_else(DeltaError::truncated)?;
+        let val = byte & (0x80 >> (self.pos % 8)) != 0;
+        self.pos += 1;
+        Ok(val)
+    }
+
+    #[inline]
+    fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> {
+        let mut val = 0;
+        for _ in 0 .. bits {
+            val = (val << 1) | u64::from(self.read_bool()?);
+        }
+        Ok(val)
+    }
+}
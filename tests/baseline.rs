@@ -0,0 +1,77 @@
+#[macro_use]
+extern crate delta_encode;
+
+use delta_encode::{BaselineReceiver, BaselineStore, DeltaError};
+use delta_encode::bitio;
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct State {
+    #[delta_bits = "10"]
+    x: u32,
+    name: String,
+}
+
+fn send(store: &mut BaselineStore<State>, val: &State) -> (u32, Vec<u8>) {
+    let mut output = bitio::Writer::new(vec![]);
+    let seq = store.encode(val, &mut output).unwrap();
+    (seq, output.finish().unwrap())
+}
+
+fn receive(store: &mut BaselineReceiver<State>, data: Vec<u8>) -> Result<(u32, State), DeltaError> {
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    store.decode(&mut r).map(|(seq, v)| (seq, v.clone()))
+}
+
+#[test]
+fn acked_baselines() {
+    let mut sender = BaselineStore::new(8);
+    let mut receiver = BaselineReceiver::new(8);
+
+    let mut val = State { x: 1, name: "hello".into() };
+    let (seq, data) = send(&mut sender, &val);
+    assert_eq!(receive(&mut receiver, data).unwrap(), (seq, val.clone()));
+    sender.ack(seq);
+    assert_eq!(sender.baseline(), Some((seq, &val)));
+
+    // Lost packets don't matter as long as the baseline was acked
+    for x in 2 .. 5 {
+        val.x = x;
+        send(&mut sender, &val);
+    }
+    val.x = 5;
+    let (seq, data) = send(&mut sender, &val);
+    assert_eq!(receive(&mut receiver, data).unwrap(), (seq, val.clone()));
+    sender.ack(seq);
+    assert_eq!(sender.baseline().map(|v| v.0), Some(seq));
+
+    // Older acks are ignored
+    sender.ack(seq - 1);
+    assert_eq!(sender.baseline().map(|v| v.0), Some(seq));
+}
+
+#[test]
+fn evicted_baseline() {
+    let mut sender = BaselineStore::new(8);
+    let mut receiver = BaselineReceiver::new(2);
+
+    let mut val = State { x: 1, name: "hello".into() };
+    let (seq, data) = send(&mut sender, &val);
+    receive(&mut receiver, data).unwrap();
+    sender.ack(seq);
+
+    // The acks never make it back so the receiver ends up evicting
+    // the baseline the sender is still using
+    for x in 2 .. 4 {
+        val.x = x;
+        let (_, data) = send(&mut sender, &val);
+        receive(&mut receiver, data).unwrap();
+    }
+    assert_eq!(sender.baseline().map(|v| v.0), Some(seq));
+
+    val.x = 4;
+    let (_, data) = send(&mut sender, &val);
+    match receive(&mut receiver, data) {
+        Err(DeltaError::MissingBaseline { .. }) => {},
+        v => panic!("Unexpected result {:?}", v),
+    }
+}
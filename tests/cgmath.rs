@@ -0,0 +1,70 @@
+#![cfg(feature = "cgmath")]
+
+#[macro_use]
+extern crate delta_encode;
+
+mod common;
+
+use cgmath::{Deg, Euler, InnerSpace, Matrix4, Point3, Quaternion, Rad, Vector2, Vector3};
+use common::{round_trip, round_trip_lossy};
+
+#[test]
+fn vectors() {
+    let base = Vector3::new(1.0f32, 2.0, 3.0);
+    assert_eq!(round_trip(&base, None), 3 * 33);
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+    assert_eq!(round_trip(&Vector3::new(1.0, 2.5, 3.0), Some(&base)), 1 + 3 + 32);
+
+    let base = Vector2::new(1.0f64, -1.0);
+    assert_eq!(round_trip(&Vector2::new(1.0, 0.0), Some(&base)), 1 + 2 + 64);
+    round_trip(&Point3::new(4.0f64, 5.0, 6.0), Some(&Point3::new(4.0, 5.0, 7.0)));
+}
+
+#[test]
+fn rotations() {
+    let base = Quaternion::new(1.0f32, 0.0, 0.0, 0.0);
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+    assert_eq!(round_trip(&Quaternion::new(0.0f32, 0.0, 1.0, 0.0), Some(&base)), 1 + 2 + 3 * 15);
+
+    // Rotations are sent using the smallest three encoding
+    let rotation = Quaternion::from(Euler::new(Deg(10.0f64), Deg(-35.0), Deg(80.0)));
+    let (decoded, bits) = round_trip_lossy(&rotation, Some(&base.cast().unwrap()));
+    assert_eq!(bits, 1 + 2 + 3 * 15);
+    assert!((decoded - rotation).magnitude() < 1e-4, "{:?} != {:?}", decoded, rotation);
+    // Negated quaternions are the same rotation
+    let (decoded, _) = round_trip_lossy(&-rotation, None);
+    assert!((decoded - rotation).magnitude() < 1e-4, "{:?} != {:?}", decoded, rotation);
+    let (_, bits) = round_trip_lossy(&(rotation * 2.0), Some(&rotation));
+    assert_eq!(bits, 1);
+
+    let base = Matrix4::from_scale(2.0f64);
+    let mut moved = base;
+    moved.w.x = 10.0;
+    // Only the last column has changed
+    assert_eq!(round_trip(&moved, Some(&base)), 1 + 3 + 1 + 4 + 64);
+
+    let base = Euler::new(Deg(10.0f32), Deg(20.0), Deg(30.0));
+    round_trip(&Euler::new(Deg(10.0f32), Deg(25.0), Deg(30.0)), Some(&base));
+    round_trip(&Rad(1.5f64), None);
+}
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Transform {
+    position: Point3<f32>,
+    rotation: Quaternion<f32>,
+    scale: f32,
+}
+
+#[test]
+fn derived() {
+    let base = Transform {
+        position: Point3::new(0.0, 1.0, 2.0),
+        rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        scale: 1.0,
+    };
+    round_trip(&base, None);
+
+    let mut moved = base.clone();
+    moved.position.y = 3.0;
+    round_trip(&moved, Some(&base));
+}
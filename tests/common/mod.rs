@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use delta_encode::DeltaEncodable;
+use delta_encode::bitio;
+
+/// Encodes `val` against `base` and decodes it again, returning the
+/// decoded value and the number of bits `encoded_bits` reports
+pub fn round_trip_lossy<T>(val: &T, base: Option<&T>) -> (T, usize)
+    where T: DeltaEncodable
+{
+    let mut output = bitio::Writer::new(vec![]);
+    val.encode(base, &mut output).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    (T::decode(base, &mut r).unwrap(), val.encoded_bits(base))
+}
+
+/// Like `round_trip_lossy` but checks that `val` is decoded unchanged
+pub fn round_trip<T>(val: &T, base: Option<&T>) -> usize
+    where T: DeltaEncodable + PartialEq + std::fmt::Debug
+{
+    let (decoded, bits) = round_trip_lossy(val, base);
+    assert_eq!(&decoded, val);
+    bits
+}
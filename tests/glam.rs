@@ -0,0 +1,68 @@
+#![cfg(feature = "glam")]
+
+#[macro_use]
+extern crate delta_encode;
+
+mod common;
+
+use common::{round_trip, round_trip_lossy};
+use glam::{Affine3A, DVec3, IVec2, Mat4, Quat, Vec3, Vec3A};
+
+#[test]
+fn vectors() {
+    let base = Vec3::new(1.0, 2.0, 3.0);
+    assert_eq!(round_trip(&base, None), 3 * 33);
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+    assert_eq!(round_trip(&Vec3::new(1.0, 2.5, 3.0), Some(&base)), 1 + 3 + 32);
+
+    let base = Vec3A::new(1.0, 2.0, 3.0);
+    assert_eq!(round_trip(&Vec3A::new(0.0, 2.0, 3.0), Some(&base)), 1 + 3 + 32);
+    round_trip(&DVec3::new(4.0, 5.0, 6.0), Some(&DVec3::ZERO));
+    assert_eq!(round_trip(&IVec2::new(-3, 7), Some(&IVec2::new(-3, 8))), 1 + 2 + 32);
+}
+
+#[test]
+fn transforms() {
+    let base = Quat::from_rotation_y(0.5);
+    assert_eq!(round_trip(&Quat::IDENTITY, Some(&base)), 1 + 2 + 3 * 15);
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+
+    // Rotations are sent using the smallest three encoding
+    let rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.3, -1.2, 2.0);
+    let (decoded, bits) = round_trip_lossy(&rotation, Some(&base));
+    assert_eq!(bits, 1 + 2 + 3 * 15);
+    assert!(decoded.abs_diff_eq(rotation, 1e-4), "{:?} != {:?}", decoded, rotation);
+    let (decoded, _) = round_trip_lossy(&-rotation, None);
+    assert!(decoded.abs_diff_eq(rotation, 1e-4), "{:?} != {:?}", decoded, rotation);
+
+    let base = Mat4::from_scale(Vec3::splat(2.0));
+    let moved = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), Quat::IDENTITY, Vec3::X);
+    // Only the translation column has changed
+    assert_eq!(round_trip(&moved, Some(&base)), 1 + 3 + 1 + 4 + 32);
+
+    let base = Affine3A::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    round_trip(&base, None);
+    round_trip(&Affine3A::from_rotation_z(0.25), Some(&base));
+}
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Body {
+    position: Vec3A,
+    rotation: Quat,
+    #[delta_bits = "8"]
+    id: u32,
+}
+
+#[test]
+fn derived() {
+    let base = Body {
+        position: Vec3A::new(0.0, 1.0, 2.0),
+        rotation: Quat::IDENTITY,
+        id: 4,
+    };
+    round_trip(&base, None);
+
+    let mut moved = base.clone();
+    moved.position.y = 3.0;
+    round_trip(&moved, Some(&base));
+}
@@ -0,0 +1,118 @@
+#[macro_use]
+extern crate delta_encode;
+
+mod common;
+
+use common::round_trip_lossy;
+use delta_encode::{bitio, DeltaEncodable, DeltaError, InternReader, InternWriter, StringTable};
+use std::sync::Arc;
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Item {
+    #[delta_interned]
+    name: String,
+    #[delta_interned]
+    icon: Arc<str>,
+    #[delta_bits = "8"]
+    count: u32,
+}
+
+fn encode<T>(val: &T, base: Option<&T>, table: &StringTable) -> Vec<u8>
+    where T: DeltaEncodable
+{
+    let mut output = bitio::Writer::new(vec![]);
+    val.encode(base, &mut InternWriter::new(&mut output, table)).unwrap();
+    output.finish().unwrap()
+}
+
+fn decode<T>(data: Vec<u8>, base: Option<&T>, table: &StringTable) -> Result<T, DeltaError>
+    where T: DeltaEncodable
+{
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    T::decode(base, &mut InternReader::new(&mut r, table))
+}
+
+fn bits<T>(val: &T, base: Option<&T>, table: &StringTable) -> usize
+    where T: DeltaEncodable
+{
+    let mut counter = delta_encode::BitCounter::new();
+    val.encode(base, &mut InternWriter::new(&mut counter, table)).unwrap();
+    counter.bits()
+}
+
+#[test]
+fn table_sync() {
+    let mut table = StringTable::new();
+    assert_eq!(table.intern("sword"), 0);
+    assert_eq!(table.intern("shield"), 1);
+    assert_eq!(table.intern("sword"), 0);
+
+    let (decoded, _) = round_trip_lossy(&table, None);
+    assert_eq!(decoded, table);
+
+    // Only the new strings are sent against an older copy
+    let old = table.clone();
+    table.intern("potion");
+    let (decoded, bits) = round_trip_lossy(&table, Some(&old));
+    assert_eq!(decoded, table);
+    assert_eq!(decoded.id("potion"), Some(2));
+    assert_eq!(bits, 1 + 1 + 8 + 8 + 6 * 8);
+
+    let (decoded, bits) = round_trip_lossy(&table, Some(&table));
+    assert_eq!(decoded, table);
+    assert_eq!(bits, 1);
+
+    // A table that doesn't extend the base is sent in full
+    let mut other = StringTable::new();
+    other.intern("bow");
+    let (decoded, _) = round_trip_lossy(&other, Some(&table));
+    assert_eq!(decoded, other);
+}
+
+#[test]
+fn interned_fields() {
+    let mut table = StringTable::new();
+    table.intern("Iron Sword");
+    table.intern("icons/weapons/iron_sword.png");
+
+    let item = Item {
+        name: "Iron Sword".into(),
+        icon: "icons/weapons/iron_sword.png".into(),
+        count: 1,
+    };
+    let decoded: Item = decode(encode(&item, None, &table), None, &table).unwrap();
+    assert_eq!(decoded, item);
+    assert_eq!(bits(&item, None, &table), (1 + 1 + 8) * 2 + 1 + 8);
+
+    // Strings missing from the table are sent as text
+    let renamed = Item { name: "Iron Sword +1".into(), ..item.clone() };
+    let decoded: Item = decode(encode(&renamed, Some(&item), &table), Some(&item), &table).unwrap();
+    assert_eq!(decoded, renamed);
+
+    let decoded: Item = decode(encode(&item, Some(&renamed), &table), Some(&renamed), &table).unwrap();
+    assert_eq!(decoded, item);
+    assert_eq!(bits(&item, Some(&renamed), &table), (1 + 1 + 8) + 1 + 1);
+
+    // Without a table nothing is interned
+    let mut counter = delta_encode::BitCounter::new();
+    item.encode(None, &mut counter).unwrap();
+    assert!(counter.bits() > 40 * 8);
+
+    // Which is also what `encoded_bits` counts
+    assert_eq!(item.encoded_bits(None), counter.bits());
+    assert!(item.encoded_bits(Some(&renamed)) > bits(&item, Some(&renamed), &table));
+}
+
+#[test]
+fn unknown_id() {
+    let mut table = StringTable::new();
+    table.intern("Iron Sword");
+    table.intern("icon");
+    let item = Item { name: "Iron Sword".into(), icon: "icon".into(), count: 1 };
+    let data = encode(&item, None, &table);
+
+    match decode::<Item>(data, None, &StringTable::new()) {
+        Err(DeltaError::InvalidData { ref path, .. }) => assert_eq!(path.to_string(), "Item.name"),
+        other => panic!("Expected an invalid id, got {:?}", other),
+    }
+}
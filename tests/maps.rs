@@ -0,0 +1,88 @@
+#[macro_use]
+extern crate delta_encode;
+
+mod common;
+
+use common::round_trip;
+use delta_encode::DeltaEncodable;
+use delta_encode::bitio;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, DeltaEncode, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[delta_always]
+struct EntityId(#[delta_bits = "16"] u32);
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Entity {
+    #[delta_bits = "8"]
+    health: u32,
+    name: String,
+}
+
+fn entity(health: u32, name: &str) -> Entity {
+    Entity { health, name: name.into() }
+}
+
+#[test]
+fn btree_map() {
+    let mut entities = BTreeMap::new();
+    for id in 0 .. 20u32 {
+        entities.insert(EntityId(id), entity(100, "grunt"));
+    }
+    let full = round_trip(&entities, None);
+    let base = entities;
+
+    let mut changed = base.clone();
+    changed.remove(&EntityId(3));
+    changed.insert(EntityId(40), entity(50, "boss"));
+    changed.get_mut(&EntityId(7)).unwrap().health = 20;
+    let delta = round_trip(&changed, Some(&base));
+    assert!(delta < full / 4);
+
+    let same = round_trip(&changed, Some(&changed));
+    assert_eq!(same, 1);
+}
+
+#[test]
+fn hash_map() {
+    let mut entities = HashMap::new();
+    for id in 0 .. 20u32 {
+        entities.insert(EntityId(id), entity(id, "grunt"));
+    }
+    round_trip(&entities, None);
+    let base = entities;
+
+    let mut changed = base.clone();
+    changed.remove(&EntityId(0));
+    changed.insert(EntityId(21), entity(1, "new"));
+    changed.get_mut(&EntityId(5)).unwrap().name = "renamed".into();
+    round_trip(&changed, Some(&base));
+
+    // The output only depends on the contents, not the iteration order
+    let rebuilt: HashMap<_, _> = changed.iter().map(|(k, v)| (*k, v.clone())).collect();
+    let encode = |map: &HashMap<EntityId, Entity>| {
+        let mut output = bitio::Writer::new(vec![]);
+        map.encode(Some(&base), &mut output).unwrap();
+        output.finish().unwrap()
+    };
+    assert_eq!(encode(&changed), encode(&rebuilt));
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn index_map() {
+    let mut entities = indexmap::IndexMap::new();
+    for id in 0 .. 10u32 {
+        entities.insert(EntityId(id), entity(id, "grunt"));
+    }
+    round_trip(&entities, None);
+    let base = entities;
+
+    let mut changed = base.clone();
+    changed.shift_remove(&EntityId(2));
+    changed.insert(EntityId(30), entity(1, "new"));
+    changed.get_mut(&EntityId(9)).unwrap().health = 3;
+    let (decoded, _) = common::round_trip_lossy(&changed, Some(&base));
+    assert_eq!(decoded, changed);
+    assert!(decoded.keys().eq(changed.keys()));
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "nalgebra")]
+
+#[macro_use]
+extern crate delta_encode;
+
+mod common;
+
+use common::{round_trip, round_trip_lossy};
+use nalgebra::{Isometry3, Matrix3, Point3, Similarity3, UnitQuaternion, Vector2, Vector3};
+
+#[test]
+fn vectors() {
+    let base = Vector3::new(1.0f32, 2.0, 3.0);
+    assert_eq!(round_trip(&base, None), 3 * 33);
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+    assert_eq!(round_trip(&Vector3::new(1.0, 2.5, 3.0), Some(&base)), 1 + 3 + 32);
+
+    round_trip(&Vector2::new(-4i32, 9), Some(&Vector2::new(-4, 8)));
+    round_trip(&Point3::new(4.0f64, 5.0, 6.0), Some(&Point3::origin()));
+
+    let base = Matrix3::new(1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+    let mut changed = base;
+    changed[(2, 1)] = 0.0;
+    assert_eq!(round_trip(&changed, Some(&base)), 1 + 9 + 32);
+}
+
+#[test]
+fn transforms() {
+    let base = Isometry3::new(Vector3::new(1.0f32, 2.0, 3.0), Vector3::y() * 0.5);
+    let (decoded, bits) = round_trip_lossy(&base, None);
+    assert_eq!(decoded.translation, base.translation);
+    assert!(decoded.rotation.angle_to(&base.rotation) < 1e-3);
+    assert_eq!(bits, 3 * 33 + 2 + 3 * 15);
+    // A bit each for the unchanged translation and rotation
+    assert_eq!(round_trip(&base, Some(&base)), 2);
+
+    let mut moved = base;
+    moved.translation.vector.x = 5.0;
+    assert_eq!(round_trip(&moved, Some(&base)), 1 + 3 + 32 + 1);
+
+    // Rotations are sent with the smallest three encoding and stay normalized
+    let rotated = UnitQuaternion::from_euler_angles(0.1f64, 0.2, 0.3);
+    let (decoded, bits) = round_trip_lossy(&rotated, Some(&UnitQuaternion::identity()));
+    assert_eq!(bits, 1 + 2 + 3 * 15);
+    assert!(decoded.angle_to(&rotated) < 1e-4);
+    assert!((decoded.as_ref().norm() - 1.0).abs() < 1e-12);
+
+    let base = Similarity3::new(Vector3::new(1.0f64, 0.0, 0.0), Vector3::z(), 2.0);
+    let scaled = Similarity3::new(Vector3::new(1.0f64, 0.0, 0.0), Vector3::z(), 3.0);
+    assert_eq!(round_trip(&scaled, Some(&base)), 2 + 65);
+}
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Body {
+    pose: Isometry3<f32>,
+    velocity: Vector3<f32>,
+}
+
+#[test]
+fn derived() {
+    let base = Body {
+        pose: Isometry3::translation(1.0, 2.0, 3.0),
+        velocity: Vector3::zeros(),
+    };
+    round_trip(&base, None);
+
+    let mut moved = base.clone();
+    moved.velocity.y = 3.0;
+    round_trip(&moved, Some(&base));
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "range_coder")]
+
+#[macro_use]
+extern crate delta_encode;
+
+use delta_encode::{BitRead, BitWrite, DeltaEncodable, DeltaError, ModelTrainer, RangeReader, RangeWriter, StaticModel};
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+struct Player {
+    #[delta_bits = "16"]
+    #[delta_diff]
+    #[delta_subbits = "4,16"]
+    x: i32,
+    #[delta_bits = "16"]
+    y: i32,
+    health: u8,
+    name: String,
+    crouching: bool,
+    inventory: Vec<u16>,
+}
+
+fn frames() -> Vec<Player> {
+    (0 .. 200)
+        .map(|i| Player {
+            x: i * 3,
+            y: 100,
+            health: if i % 50 == 49 { 90 } else { 100 },
+            name: "player".to_owned(),
+            crouching: i % 20 < 5,
+            inventory: vec![1, 2, if i > 100 { 4 } else { 3 }],
+        })
+        .collect()
+}
+
+#[test]
+fn round_trip_stream() {
+    let frames = frames();
+
+    let mut w = RangeWriter::new();
+    let mut base: Option<&Player> = None;
+    let mut raw_bits = 0;
+    for frame in &frames {
+        frame.encode(base, &mut w).unwrap();
+        raw_bits += frame.encoded_bits(base);
+        base = Some(frame);
+    }
+    let data = w.finish();
+    // Most frames only change a couple of fields by the same amount
+    assert!(data.len() * 8 < raw_bits / 3, "{} bytes vs {} bits", data.len(), raw_bits);
+
+    let mut r = RangeReader::new(&data).unwrap();
+    let mut base: Option<Player> = None;
+    for frame in &frames {
+        let decoded = Player::decode(base.as_ref(), &mut r).unwrap();
+        assert_eq!(&decoded, frame);
+        base = Some(decoded);
+    }
+}
+
+#[test]
+fn blocks_share_the_model() {
+    let frames = frames();
+
+    let mut w = RangeWriter::with_context_bits(12);
+    let blocks: Vec<Vec<u8>> = frames.windows(2)
+        .map(|pair| {
+            pair[1].encode(Some(&pair[0]), &mut w).unwrap();
+            w.take_block()
+        })
+        .collect();
+    // Later blocks benefit from what the earlier ones learnt
+    let size = |blocks: &[Vec<u8>]| blocks.iter().map(Vec::len).sum::<usize>();
+    assert!(size(&blocks[blocks.len() - 10 ..]) < size(&blocks[.. 10]));
+
+    let mut r = RangeReader::with_context_bits(&blocks[0], 12).unwrap();
+    for (idx, block) in blocks.iter().enumerate() {
+        if idx > 0 {
+            r.next_block(block).unwrap();
+        }
+        let decoded = Player::decode(Some(&frames[idx]), &mut r).unwrap();
+        assert_eq!(decoded, frames[idx + 1]);
+    }
+}
+
+#[test]
+fn truncated() {
+    let frames = frames();
+    let mut w = RangeWriter::new();
+    frames[10].encode(None, &mut w).unwrap();
+    let data = w.finish();
+
+    let mut r = RangeReader::new(&data[.. data.len() / 2]).unwrap();
+    match Player::decode(None, &mut r) {
+        Err(DeltaError::Truncated { .. }) => {},
+        other => panic!("Expected truncated, got {:?}", other),
+    }
+}
+
+mod models {
+    use delta_encode::StaticModel;
+
+    pub static PLAYER: StaticModel = StaticModel::UNTRAINED;
+    pub static HEALTH: StaticModel = StaticModel::UNTRAINED;
+}
+
+#[derive(Debug, DeltaEncode, PartialEq, Clone)]
+#[delta_model = "models::PLAYER"]
+struct Modelled {
+    #[delta_bits = "16"]
+    #[delta_diff]
+    #[delta_subbits = "4,16"]
+    x: i32,
+    #[delta_model = "models::HEALTH"]
+    health: u8,
+    crouching: bool,
+}
+
+fn modelled_frames() -> Vec<Modelled> {
+    frames().into_iter()
+        .map(|p| Modelled { x: p.x, health: p.health, crouching: p.crouching })
+        .collect()
+}
+
+#[test]
+fn untrained_models() {
+    let frames = modelled_frames();
+    let mut w = RangeWriter::new();
+    for pair in frames.windows(2) {
+        pair[1].encode(Some(&pair[0]), &mut w).unwrap();
+    }
+    let data = w.finish();
+
+    let mut r = RangeReader::new(&data).unwrap();
+    for pair in frames.windows(2) {
+        assert_eq!(Modelled::decode(Some(&pair[0]), &mut r).unwrap(), pair[1]);
+    }
+}
+
+#[test]
+fn trained_models() {
+    let frames = frames();
+
+    let mut trainer = ModelTrainer::new(10);
+    for pair in frames.windows(2) {
+        pair[1].encode(Some(&pair[0]), &mut trainer).unwrap();
+    }
+    let module = trainer.to_module("PLAYER");
+    assert!(module.contains("pub static PLAYER: delta_encode::StaticModel"));
+    // Three header lines, sixteen probabilities per line and the footer
+    assert_eq!(module.lines().count(), 3 + (1 << 10) / 16 + 2);
+
+    let probs: &'static [u16] = Box::leak(trainer.probs().into_boxed_slice());
+    let trained: &'static StaticModel = Box::leak(Box::new(StaticModel { probs }));
+
+    let encode = |model: &'static StaticModel| {
+        let mut w = RangeWriter::new();
+        for pair in frames.windows(2) {
+            w.enter_model(model);
+            pair[1].encode(Some(&pair[0]), &mut w).unwrap();
+            w.leave_model();
+        }
+        w.finish()
+    };
+    let data = encode(trained);
+    assert!(data.len() < encode(&StaticModel::UNTRAINED).len() / 2);
+
+    let mut r = RangeReader::new(&data).unwrap();
+    for pair in frames.windows(2) {
+        r.enter_model(trained);
+        assert_eq!(Player::decode(Some(&pair[0]), &mut r).unwrap(), pair[1]);
+        r.leave_model();
+    }
+}
+
+#[test]
+fn trainer_only_counts_its_model() {
+    let frames = modelled_frames();
+    let mut trainer = ModelTrainer::new(8).only(&models::HEALTH);
+    for pair in frames.windows(2) {
+        pair[1].encode(Some(&pair[0]), &mut trainer).unwrap();
+    }
+    // Only the health change bit and the bits of its two values were
+    // counted, so most probabilities are still even
+    let probs = trainer.probs();
+    let trained = probs.iter().filter(|&&p| p != 1024).count();
+    assert!(trained > 0 && trained <= 15, "{} trained", trained);
+}
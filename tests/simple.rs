@@ -2,6 +2,9 @@
 #[macro_use]
 extern crate delta_encode;
 
+mod common;
+
+use common::{round_trip, round_trip_lossy};
 use delta_encode::DeltaEncodable;
 use delta_encode::bitio;
 
@@ -196,4 +199,957 @@ fn floats() {
     let mut r = bitio::Reader::new(std::io::Cursor::new(data));
     let decoded_val2 = TestFloats::decode(Some(&decoded_val), &mut r).unwrap();
     println!("{:?}", decoded_val2);
-}
\ No newline at end of file
+}
+#[test]
+fn custom_bit_io() {
+    use delta_encode::{BitRead, BitWrite, DeltaError};
+
+    struct Recorder(Vec<bool>);
+    impl BitWrite for Recorder {
+        fn write_bool(&mut self, val: bool) -> Result<(), DeltaError> {
+            self.0.push(val);
+            Ok(())
+        }
+
+        fn write_unsigned(&mut self, val: u64, bits: u8) -> Result<(), DeltaError> {
+            for i in (0 .. bits).rev() {
+                self.0.push((val >> i) & 1 == 1);
+            }
+            Ok(())
+        }
+    }
+
+    struct Replay(std::vec::IntoIter<bool>);
+    impl BitRead for Replay {
+        fn read_bool(&mut self) -> Result<bool, DeltaError> {
+            self.0.next().ok_or_else(DeltaError::truncated)
+        }
+
+        fn read_unsigned(&mut self, bits: u8) -> Result<u64, DeltaError> {
+            let mut val = 0;
+            for _ in 0 .. bits {
+                val = (val << 1) | self.read_bool()? as u64;
+            }
+            Ok(val)
+        }
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Testing {
+        #[delta_bits = "6"]
+        a: i32,
+        b: f64,
+        name: String,
+        list: Vec<Option<String>>,
+    }
+
+    let test_val = Testing {
+        a: -12,
+        b: 3.5,
+        name: "hello".into(),
+        list: vec![Some("a".into()), None],
+    };
+
+    let mut rec = Recorder(vec![]);
+    test_val.encode(None, &mut rec).unwrap();
+    let decoded = Testing::decode(None, &mut Replay(rec.0.into_iter())).unwrap();
+    assert_eq!(decoded, test_val);
+
+    let changed = Testing {
+        a: 7,
+        .. test_val.clone()
+    };
+    let mut rec = Recorder(vec![]);
+    changed.encode(Some(&test_val), &mut rec).unwrap();
+    let decoded = Testing::decode(Some(&test_val), &mut Replay(rec.0.into_iter())).unwrap();
+    assert_eq!(decoded, changed);
+}
+
+#[test]
+fn error_paths() {
+    use delta_encode::DeltaError;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Player {
+        inventory: Vec<Item>,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Item {
+        #[delta_subbits = "2, 4"]
+        count: u32,
+    }
+
+    let player = Player {
+        inventory: vec![Item { count: 1 }, Item { count: 2 }, Item { count: 3 }, Item { count: 100 }],
+    };
+
+    let mut output = bitio::Writer::new(vec![]);
+    let err = player.encode(None, &mut output).unwrap_err();
+    match err {
+        DeltaError::OutOfRange { value: 100, bits: 4, .. } => {},
+        ref e => panic!("Unexpected error {:?}", e),
+    }
+    assert_eq!(err.path().to_string(), "Player.inventory[3].count");
+
+    let player = Player {
+        inventory: vec![Item { count: 1 }],
+    };
+    let mut output = bitio::Writer::new(vec![]);
+    player.encode(Some(&player), &mut output).unwrap();
+    let data = output.finish().unwrap();
+
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    match Player::decode(None, &mut r) {
+        Err(DeltaError::MissingBaseline { ref path }) => assert_eq!(path.to_string(), "Player.inventory"),
+        v => panic!("Unexpected result {:?}", v),
+    }
+
+    let mut r = bitio::Reader::new(std::io::Cursor::new(vec![]));
+    match Player::decode(None, &mut r) {
+        Err(DeltaError::Truncated { .. }) => {},
+        v => panic!("Unexpected result {:?}", v),
+    }
+}
+
+#[test]
+fn slice_io() {
+    use delta_encode::{DeltaError, SliceReader, SliceWriter};
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Testing {
+        #[delta_bits = "12"]
+        a: u32,
+        b: Option<f32>,
+        c: [i8; 3],
+    }
+
+    let test_val = Testing {
+        a: 1000,
+        b: Some(2.5),
+        c: [-1, 0, 1],
+    };
+
+    let mut buf = [0; 16];
+    let mut w = SliceWriter::new(&mut buf);
+    test_val.encode(None, &mut w).unwrap();
+    let data = w.finish();
+
+    let decoded = Testing::decode(None, &mut SliceReader::new(data)).unwrap();
+    assert_eq!(decoded, test_val);
+
+    let mut buf = [0; 2];
+    match test_val.encode(None, &mut SliceWriter::new(&mut buf)) {
+        Err(DeltaError::Truncated { .. }) => {},
+        v => panic!("Unexpected result {:?}", v),
+    }
+}
+
+#[test]
+fn encoded_bits() {
+    use delta_encode::SliceWriter;
+    use std::sync::Arc;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Testing {
+        #[delta_bits = "5"]
+        a: u32,
+        #[delta_subbits = "4, 8, 16"]
+        b: i32,
+        name: Arc<str>,
+        list: Vec<Option<f32>>,
+        always: delta_encode::AlwaysVec<String>,
+    }
+
+    let test_val = Testing {
+        a: 3,
+        b: 200,
+        name: "name".into(),
+        list: vec![Some(1.0), None],
+        always: delta_encode::AlwaysVec(vec!["a".into()]),
+    };
+    let changed = Testing {
+        b: -2,
+        list: vec![Some(1.0), None, Some(2.0)],
+        .. test_val.clone()
+    };
+
+    for &(val, base) in &[(&test_val, None), (&changed, Some(&test_val)), (&changed, Some(&changed))] {
+        let mut buf = [0; 64];
+        let mut w = SliceWriter::new(&mut buf);
+        val.encode(base, &mut w).unwrap();
+        assert_eq!(val.encoded_bits(base), w.bit_len());
+    }
+    assert!(changed.encoded_bits(Some(&changed)) < changed.encoded_bits(None));
+}
+
+#[test]
+fn diff_vec() {
+    use delta_encode::DiffVec;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Item {
+        #[delta_bits = "10"]
+        id: u32,
+        #[delta_bits = "7"]
+        count: u8,
+    }
+
+    let base = DiffVec((0 .. 50).map(|id| Item { id, count: 1 }).collect::<Vec<_>>());
+
+    let mut inserted = base.clone();
+    inserted.0.insert(0, Item { id: 999, count: 5 });
+    let bits = round_trip(&inserted, Some(&base));
+    assert!(bits < 64, "Insert cost {} bits", bits);
+    assert!(bits < inserted.0.clone().encoded_bits(Some(&base.0)));
+
+    let mut edited = base.clone();
+    edited.0.remove(10);
+    edited.0.remove(30);
+    edited.0[20].count = 3;
+    edited.0.push(Item { id: 100, count: 2 });
+    round_trip(&edited, Some(&base));
+
+    round_trip(&DiffVec(vec![]), Some(&base));
+    round_trip(&base, Some(&DiffVec(vec![])));
+
+    let mut output = bitio::Writer::new(vec![]);
+    base.encode(None, &mut output).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    assert_eq!(DiffVec::decode(None, &mut r).unwrap(), base);
+}
+
+#[test]
+fn vec_changes() {
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Item {
+        #[delta_bits = "10"]
+        id: u32,
+        #[delta_bits = "7"]
+        count: u8,
+    }
+
+    let base = (0 .. 500).map(|id| Item { id, count: 1 }).collect::<Vec<_>>();
+    assert_eq!(round_trip(&base, Some(&base)), 1);
+
+    let mut single = base.clone();
+    single[321].count = 4;
+    let bits = round_trip(&single, Some(&base));
+    assert!(bits < 64, "Single change cost {} bits", bits);
+
+    let mut sparse = base.clone();
+    sparse[3].count = 120;
+    sparse[150].id = 999;
+    sparse[151].count = 0;
+    sparse[499] = Item { id: 7, count: 64 };
+    let bits = round_trip(&sparse, Some(&base));
+    assert!(bits < 200, "Sparse changes cost {} bits", bits);
+
+    // Changing most elements falls back to a bit per element
+    let mut most = base.clone();
+    for item in most.iter_mut().step_by(2) {
+        item.count = 9;
+    }
+    let bits = round_trip(&most, Some(&base));
+    assert!(bits < 500 + 250 * 20, "Many changes cost {} bits", bits);
+
+    let mut grown = single.clone();
+    grown.push(Item { id: 1000, count: 3 });
+    round_trip(&grown, Some(&base));
+    round_trip(&base[.. 10].to_vec(), Some(&base));
+    round_trip(&base, Some(&vec![]));
+    round_trip(&base, None);
+}
+
+#[test]
+fn string_splice() {
+    let str_round_trip = |val: &str, base: Option<&str>| {
+        round_trip(&val.to_owned(), base.map(|v| v.to_owned()).as_ref())
+    };
+
+    let base = "Player joined the lobby, waiting for players";
+    let full = str_round_trip(base, None);
+    assert_eq!(str_round_trip(base, Some(base)), 1);
+
+    let bits = str_round_trip("Player joined the lobby, waiting for players...", Some(base));
+    assert!(bits < 64, "Append cost {} bits", bits);
+    let bits = str_round_trip("Player joined the arena, waiting for players", Some(base));
+    assert!(bits < 96, "Edit cost {} bits", bits);
+
+    // Unrelated strings are sent in full
+    assert_eq!(str_round_trip(base, Some("xyz")), full + 1);
+    str_round_trip("", Some(base));
+    str_round_trip("Zoë → ünïcode", Some("Zoe → unicode"));
+    str_round_trip("aaaa", Some("aa"));
+}
+
+#[test]
+fn primitives() {
+    use std::sync::Arc;
+
+    type EntityId = u32;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct State {
+        owner: EntityId,
+        ids: Vec<u32>,
+        flags: Vec<Option<bool>>,
+        total: Arc<u64>,
+        offsets: Vec<f64>,
+        delta: isize,
+    }
+
+    let base = State {
+        owner: 7,
+        ids: vec![1, 2, 3],
+        flags: vec![Some(true), None],
+        total: Arc::new(1 << 40),
+        offsets: vec![0.5, -1.0],
+        delta: -3,
+    };
+    round_trip(&base, None);
+
+    let mut changed = base.clone();
+    changed.ids[1] = u32::MAX;
+    changed.delta = isize::MIN;
+    round_trip(&changed, Some(&base));
+
+    assert_eq!(round_trip(&42u8, Some(&42)), 1);
+    assert_eq!(round_trip(&42u8, Some(&43)), 9);
+    assert_eq!(round_trip(&-1i16, None), 17);
+    assert_eq!(round_trip(&usize::MAX, None), 65);
+    assert_eq!(round_trip(&true, Some(&true)), 1);
+    assert_eq!(round_trip(&f64::NAN.to_bits(), Some(&f64::NAN.to_bits())), 1);
+    assert_eq!(round_trip(&1.5f32, Some(&1.5)), 1);
+    assert_eq!(round_trip(&1.5f64, Some(&2.0)), 65);
+}
+
+#[test]
+fn containers() {
+    use std::borrow::Cow;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Unit {
+        #[delta_bits = "10"]
+        pos: (i32, i32),
+        name: Box<str>,
+        path: Box<[(u8, bool)]>,
+        shared: Arc<[u16]>,
+        label: Cow<'static, str>,
+        tags: Cow<'static, [u8]>,
+        boxed: Box<u32>,
+        counted: Rc<(u8, u8, u8)>,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    enum Order {
+        Move {
+            #[delta_bits = "8"]
+            target: (u16, (u8, u8)),
+        },
+        Stop,
+    }
+
+    let base = Unit {
+        pos: (-5, 300),
+        name: "scout".into(),
+        path: vec![(1, true), (2, false)].into(),
+        shared: vec![10, 20, 30].into(),
+        label: Cow::Borrowed("idle"),
+        tags: Cow::Borrowed(&[1, 2, 3]),
+        boxed: Box::new(9),
+        counted: Rc::new((1, 2, 3)),
+    };
+    round_trip(&base, None);
+
+    let mut changed = base.clone();
+    changed.pos.1 = 301;
+    changed.name = "scouts".into();
+    changed.shared = vec![10, 21, 30, 40].into();
+    changed.label = Cow::Owned("moving".into());
+    changed.counted = Rc::new((1, 5, 3));
+    round_trip(&changed, Some(&base));
+
+    // Only the second coordinate is sent
+    let mut moved = base.clone();
+    moved.pos.1 = 299;
+    assert_eq!(
+        round_trip(&moved, Some(&base)),
+        round_trip(&base, Some(&base)) + 10
+    );
+
+    let order = Order::Move { target: (4, (5, 6)) };
+    round_trip(&order, None);
+    round_trip(&Order::Move { target: (4, (5, 7)) }, Some(&order));
+    round_trip(&Order::Stop, Some(&order));
+
+    round_trip(&(1u8, -2i64, 0.5f32, true, String::from("a"), 6u16, 7u32, 8u64, 9i8, 10i16, 11i32, 12usize), None);
+
+    let mut output = bitio::Writer::new(vec![]);
+    moved.encode(Some(&base), &mut output).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    let err = Unit::decode(None, &mut r).unwrap_err();
+    assert!(err.to_string().ends_with("at Unit.pos[0]"), "{}", err);
+
+    // Tuple elements are written within the context of their field
+    struct Contexts(Vec<u32>);
+    impl delta_encode::BitWrite for Contexts {
+        fn write_bool(&mut self, _val: bool) -> Result<(), delta_encode::DeltaError> {
+            Ok(())
+        }
+
+        fn write_unsigned(&mut self, _val: u64, _bits: u8) -> Result<(), delta_encode::DeltaError> {
+            Ok(())
+        }
+
+        fn enter_context(&mut self, id: u32) {
+            self.0.push(id);
+        }
+    }
+    let mut contexts = Contexts(vec![]);
+    base.encode(None, &mut contexts).unwrap();
+    assert_eq!(contexts.0.len(), 8);
+    let mut contexts = Contexts(vec![]);
+    order.encode(None, &mut contexts).unwrap();
+    assert_eq!(contexts.0.len(), 1);
+}
+
+#[test]
+fn large_arrays() {
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Inventory {
+        #[delta_bits = "6"]
+        slots: [u8; 64],
+        table: Vec<[u16; 4]>,
+        extra: Option<[bool; 40]>,
+    }
+
+    let mut base = Inventory {
+        slots: [0; 64],
+        table: vec![[1, 2, 3, 4], [5, 6, 7, 8]],
+        extra: Some([false; 40]),
+    };
+    base.slots[63] = 17;
+    round_trip(&base, None);
+
+    let mut changed = base.clone();
+    changed.slots[40] = 3;
+    changed.table[1][2] = 70;
+    changed.extra.as_mut().unwrap()[39] = true;
+    round_trip(&changed, Some(&base));
+
+    let lookup: [u32; 256] = std::array::from_fn(|idx| idx as u32 * 3);
+    round_trip(&lookup, None);
+    round_trip(&lookup, Some(&[0; 256]));
+}
+
+#[test]
+fn array_changes() {
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Slot {
+        #[delta_bits = "10"]
+        item: u16,
+        #[delta_bits = "7"]
+        count: u8,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Inventory {
+        #[delta_bits = "6"]
+        counts: [u8; 64],
+        slots: [Slot; 8],
+        #[delta_bits = "4"]
+        grid: [[u8; 4]; 4],
+    }
+
+    let base = Inventory {
+        counts: [1; 64],
+        slots: std::array::from_fn(|idx| Slot { item: idx as u16, count: 1 }),
+        grid: [[0; 4]; 4],
+    };
+    round_trip(&base, None);
+    assert_eq!(round_trip(&base, Some(&base)), 3);
+
+    // An any-changed bit, a list of one index and the new value
+    let mut single = base.clone();
+    single.counts[50] = 7;
+    assert_eq!(round_trip(&single, Some(&base)), 3 + 1 + 8 + 8 + 6);
+
+    let mut many = base.clone();
+    for count in many.counts.iter_mut().step_by(3) {
+        *count = 2;
+    }
+    many.slots[5].count = 9;
+    many.grid[2][1] = 15;
+    round_trip(&many, Some(&base));
+
+    // Several changes far apart are sent as a list of indices
+    let mut sparse = base.clone();
+    sparse.counts[3] = 40;
+    sparse.counts[60] = 63;
+    sparse.slots[0].item = 700;
+    sparse.slots[7] = Slot { item: 1000, count: 100 };
+    let bits = round_trip(&sparse, Some(&base));
+    assert!(bits < 100, "Sparse changes cost {} bits", bits);
+
+    // Elements under the change set don't send a changed bit of their own
+    #[derive(Debug, DeltaEncode, PartialEq, Clone, Copy)]
+    enum Tile {
+        Empty,
+        Wall,
+        Door,
+        Water,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Level {
+        tiles: [Tile; 4],
+        #[delta_bits = "2"]
+        heights: [u8; 4],
+        #[delta_bits = "2"]
+        grid: [[u8; 4]; 4],
+    }
+
+    let base = Level {
+        tiles: [Tile::Empty; 4],
+        heights: [0; 4],
+        grid: [[0; 4]; 4],
+    };
+    let mut tiles = base.clone();
+    tiles.tiles[2] = Tile::Door;
+    let mut heights = base.clone();
+    heights.heights[2] = 2;
+    let mut grid = base.clone();
+    grid.grid[2][2] = 2;
+    let single = round_trip(&heights, Some(&base));
+    assert_eq!(round_trip(&tiles, Some(&base)), single);
+    // The inner array pays for its change set but not an any-changed bit
+    assert_eq!(round_trip(&grid, Some(&base)), single + (single - 3 - 2));
+
+    let bytes = [0u8; 4];
+    let mut changed_bytes = bytes;
+    changed_bytes[1] = 3;
+    let changed_tiles = [Tile::Empty, Tile::Water, Tile::Empty, Tile::Empty];
+    assert_eq!(
+        round_trip(&changed_tiles, Some(&[Tile::Empty; 4])) - 2,
+        round_trip(&changed_bytes, Some(&bytes)) - 8
+    );
+
+    let lookup: [u32; 100] = std::array::from_fn(|idx| idx as u32);
+    let mut changed = lookup;
+    changed[99] = 0;
+    assert_eq!(round_trip(&lookup, Some(&lookup)), 1);
+    assert!(round_trip(&changed, Some(&lookup)) < 64);
+
+    let mut changed = lookup;
+    changed[10] = 0xDEAD_BEEF;
+    changed[90] = 7;
+    assert!(round_trip(&changed, Some(&lookup)) < 100);
+
+    let lookup = lookup.to_vec();
+    let mut changed = lookup.clone();
+    changed[10] = 0xDEAD_BEEF;
+    changed[50] = 1;
+    changed[90] = 7;
+    assert!(round_trip(&changed, Some(&lookup)) < 200);
+}
+
+#[test]
+fn float_ranges() {
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Position {
+        #[delta_range = "-512.0..512.0"]
+        #[delta_precision = "0.01"]
+        x: f32,
+        #[delta_range = "0..1"]
+        #[delta_bits = "8"]
+        alpha: f64,
+        #[delta_range = "-1.0..1.0"]
+        #[delta_bits = "10"]
+        #[delta_always]
+        dir: f32,
+    }
+
+    let val = Position { x: 123.456, alpha: 0.5, dir: -0.25 };
+    let (decoded, bits) = round_trip_lossy(&val, None);
+    assert_eq!(bits, 1 + 17 + 1 + 8 + 10);
+    assert!((decoded.x - val.x).abs() <= 0.005, "{} != {}", decoded.x, val.x);
+    assert!((decoded.alpha - val.alpha).abs() <= 0.5 / 255.0);
+    assert!((decoded.dir - val.dir).abs() <= 1.0 / 1023.0);
+
+    // Decoding the output again reconstructs exactly the same values
+    assert_eq!(round_trip_lossy(&decoded, None).0, decoded);
+
+    // Changes smaller than a step aren't sent
+    let mut nudged = decoded.clone();
+    nudged.x += 0.001;
+    let (unchanged, bits) = round_trip_lossy(&nudged, Some(&decoded));
+    assert_eq!(bits, 1 + 1 + 10);
+    assert_eq!(unchanged.x, decoded.x);
+
+    // Values outside the range are clamped
+    let clamped = Position { x: 1000.0, alpha: -3.0, dir: f32::NAN };
+    let (decoded, _) = round_trip_lossy(&clamped, None);
+    assert_eq!(decoded, Position { x: 512.0, alpha: 0.0, dir: -1.0 });
+}
+
+#[test]
+fn quaternions() {
+    use delta_encode::QuatComponents;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Rotation([f32; 4]);
+
+    impl QuatComponents for Rotation {
+        fn to_xyzw(&self) -> [f64; 4] {
+            self.0.map(f64::from)
+        }
+
+        fn from_xyzw(xyzw: [f64; 4]) -> Self {
+            Rotation(xyzw.map(|v| v as f32))
+        }
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Body {
+        #[delta_quat(bits = 10)]
+        rotation: Rotation,
+        #[delta_quat]
+        #[delta_always]
+        aim: Rotation,
+        #[delta_quat(bits = 8)]
+        bones: [Rotation; 2],
+    }
+
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+    let identity = Rotation([0.0, 0.0, 0.0, 1.0]);
+    let body = Body {
+        rotation: Rotation([0.0, half, 0.0, half]),
+        aim: Rotation([0.1, -0.2, 0.3, -0.9]),
+        bones: [identity, Rotation([0.0, 0.0, 2.0, 0.0])],
+    };
+    let (decoded, bits) = round_trip_lossy(&body, None);
+    assert_eq!(bits, (2 + 30) + (2 + 45) + 2 * (2 + 24));
+    assert_eq!(decoded.rotation, body.rotation);
+    assert_eq!(decoded.bones, [identity, Rotation([0.0, 0.0, 1.0, 0.0])]);
+
+    // The aim is normalized and flipped so its largest component is positive
+    let len = body.aim.0.iter().map(|v| v * v).sum::<f32>().sqrt();
+    for (a, b) in decoded.aim.0.iter().zip(body.aim.0.iter()) {
+        assert!((a + b / len).abs() < 1e-4, "{:?} != {:?}", decoded.aim, body.aim);
+    }
+
+    let (again, bits) = round_trip_lossy(&decoded, Some(&decoded));
+    assert_eq!(again, decoded);
+    assert_eq!(bits, 1 + (2 + 45) + 1);
+}
+
+#[test]
+fn directions() {
+    use delta_encode::UnitVector3;
+
+    type Velocity = UnitVector3<[f32; 3], 8>;
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Shot {
+        #[delta_unit_vector(bits = 10)]
+        aim: [f32; 3],
+        #[delta_unit_vector]
+        normals: [[f64; 3]; 2],
+        velocity: Velocity,
+    }
+
+    fn assert_close(a: [f64; 3], b: [f64; 3], error: f64) {
+        let len = b.iter().map(|v| v * v).sum::<f64>().sqrt();
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b / len).abs() < error, "{:?} != {:?}", a, b / len);
+        }
+    }
+
+    // The poles and axes are exact
+    for axis in [[0.0, 0.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]] {
+        let (decoded, bits) = round_trip_lossy(&UnitVector3::<[f64; 3]>(axis), None);
+        assert_eq!(decoded.0, axis);
+        assert_eq!(bits, 2 * 12);
+    }
+
+    let shot = Shot {
+        aim: [0.3, -0.5, 0.8],
+        normals: [[-0.2, 0.1, -0.9], [5.0, 5.0, 5.0]],
+        velocity: UnitVector3([0.0, 0.6, -0.8]),
+    };
+    let (decoded, bits) = round_trip_lossy(&shot, None);
+    assert_eq!(bits, 2 * 10 + 2 * 2 * 12 + 2 * 8);
+    assert_close(decoded.aim.map(f64::from), [0.3, -0.5, 0.8], 5e-3);
+    assert_close(decoded.normals[0], [-0.2, 0.1, -0.9], 2e-3);
+    assert_close(decoded.normals[1], [5.0, 5.0, 5.0], 2e-3);
+    assert_close(decoded.velocity.0.map(f64::from), [0.0, 0.6, -0.8], 1e-2);
+
+    let (again, bits) = round_trip_lossy(&decoded, Some(&decoded));
+    assert_eq!(again, decoded);
+    assert_eq!(bits, 1 + 1 + 1);
+
+    let mut turned = decoded.clone();
+    turned.aim = [0.0, 1.0, 0.0];
+    let (again, bits) = round_trip_lossy(&turned, Some(&decoded));
+    assert_eq!(again.aim, [0.0, 1.0, 0.0]);
+    assert_eq!(bits, (1 + 2 * 10) + 1 + 1);
+}
+
+#[test]
+fn integer_codes() {
+    use delta_encode::{BitCounter, DeltaError, Gamma, Rice, Varint};
+
+    type Latency = Rice<u32, 4>;
+
+    fn bits_of<F>(f: F) -> usize
+        where F: FnOnce(&mut BitCounter) -> Result<(), DeltaError>
+    {
+        let mut counter = BitCounter::new();
+        f(&mut counter).unwrap();
+        counter.bits()
+    }
+
+    assert_eq!(delta_encode::zigzag(0), 0);
+    assert_eq!(delta_encode::zigzag(-1), 1);
+    assert_eq!(delta_encode::zigzag(1), 2);
+    assert_eq!(delta_encode::zigzag(i64::MIN), u64::MAX);
+    for v in [0, -1, 1, 1234, -1234, i64::MIN, i64::MAX] {
+        assert_eq!(delta_encode::unzigzag(delta_encode::zigzag(v)), v);
+    }
+
+    assert_eq!(bits_of(|w| delta_encode::write_gamma(w, 0)), 1);
+    assert_eq!(bits_of(|w| delta_encode::write_gamma(w, 6)), 5);
+    assert_eq!(bits_of(|w| delta_encode::write_gamma(w, u64::MAX)), 129);
+    assert_eq!(bits_of(|w| delta_encode::write_varint(w, 127)), 8);
+    assert_eq!(bits_of(|w| delta_encode::write_varint(w, 128)), 16);
+    assert_eq!(bits_of(|w| delta_encode::write_rice(w, 9, 2)), 2 + 1 + 2);
+
+    for v in [0, 1, 2, 7, 100, 1 << 20, u64::MAX - 1, u64::MAX] {
+        let mut output = bitio::Writer::new(vec![]);
+        delta_encode::write_varint(&mut output, v).unwrap();
+        delta_encode::write_gamma(&mut output, v).unwrap();
+        delta_encode::write_rice(&mut output, v, 0).unwrap();
+        delta_encode::write_rice(&mut output, v, 5).unwrap();
+        delta_encode::write_rice(&mut output, v, 63).unwrap();
+        let data = output.finish().unwrap();
+        let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+        assert_eq!(delta_encode::read_varint(&mut r).unwrap(), v);
+        assert_eq!(delta_encode::read_gamma(&mut r).unwrap(), v);
+        assert_eq!(delta_encode::read_rice(&mut r, 0).unwrap(), v);
+        assert_eq!(delta_encode::read_rice(&mut r, 5).unwrap(), v);
+        assert_eq!(delta_encode::read_rice(&mut r, 63).unwrap(), v);
+    }
+
+    // A quotient too large for the parameter is rejected rather than
+    // losing its high bits
+    let mut output = bitio::Writer::new(vec![]);
+    output.write_unsigned(0b110, 3).unwrap();
+    output.write_unsigned(0, 64).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    match delta_encode::read_rice(&mut r, 63) {
+        Err(DeltaError::InvalidData { .. }) => {},
+        other => panic!("Expected invalid data, got {:?}", other),
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Stats {
+        #[delta_varint]
+        score: u32,
+        #[delta_gamma]
+        kills: u16,
+        #[delta_rice = "3"]
+        #[delta_always]
+        ping: u16,
+        #[delta_gamma]
+        #[delta_diff]
+        health: i8,
+        #[delta_gamma]
+        #[delta_diff]
+        #[delta_always]
+        tick: u64,
+        count: Varint<usize>,
+        offset: Gamma<i64>,
+        latency: Latency,
+    }
+
+    let stats = Stats {
+        score: 300,
+        kills: 0,
+        ping: 20,
+        health: -3,
+        tick: 1000,
+        count: Varint(5),
+        offset: Gamma(-1),
+        latency: Rice(17),
+    };
+    let bits = round_trip(&stats, None);
+    assert_eq!(bits,
+        (1 + 16)            // score
+        + (1 + 1)           // kills
+        + (2 + 1 + 3)       // ping
+        + (1 + 5)           // health, zigzag(-3) = 5
+        + 19                // tick
+        + (1 + 8)           // count
+        + (1 + 3)           // offset, zigzag(-1) = 1
+        + (1 + 1 + 1 + 4)   // latency
+    );
+
+    let bits = round_trip(&stats, Some(&stats));
+    assert_eq!(bits, 1 + 1 + (2 + 1 + 3) + 1 + 1 + 1 + 1 + 1);
+
+    // Differences are zigzagged within the type so that stepping over
+    // the ends of an unsigned range stays cheap
+    let mut next = stats.clone();
+    next.health = -4;
+    next.tick = 999;
+    let bits = round_trip(&next, Some(&stats));
+    assert_eq!(bits, 1 + 1 + (2 + 1 + 3) + (1 + 3) + 3 + 1 + 1 + 1);
+
+    let mut wrapped = stats.clone();
+    wrapped.tick = 0;
+    round_trip(&wrapped, Some(&Stats { tick: u64::MAX, ..stats.clone() }));
+}
+
+#[test]
+fn enum_tags() {
+    use delta_encode::DeltaError;
+
+    mod v1 {
+        #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+        #[delta_tag_bits = 4]
+        pub enum Command {
+            #[delta_tag = 1]
+            Move(#[delta_bits = "8"] i32),
+            Stop,
+            #[delta_tag = 7]
+            Say(String),
+        }
+    }
+
+    // Reordered with a new variant, the tags keep it compatible
+    mod v2 {
+        #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+        #[delta_tag_bits = 4]
+        pub enum Command {
+            #[delta_tag = 7]
+            Say(String),
+            #[delta_tag = 3]
+            Jump,
+            #[delta_tag = 1]
+            Move(#[delta_bits = "8"] i32),
+            #[delta_tag = 2]
+            Stop,
+        }
+    }
+
+    fn convert<A, B>(val: &A) -> Result<B, DeltaError>
+        where A: DeltaEncodable,
+              B: DeltaEncodable
+    {
+        let mut output = bitio::Writer::new(vec![]);
+        val.encode(None, &mut output).unwrap();
+        let data = output.finish().unwrap();
+        let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+        B::decode(None, &mut r)
+    }
+
+    assert_eq!(convert::<_, v2::Command>(&v1::Command::Move(-3)).unwrap(), v2::Command::Move(-3));
+    assert_eq!(convert::<_, v2::Command>(&v1::Command::Stop).unwrap(), v2::Command::Stop);
+    assert_eq!(convert::<_, v1::Command>(&v2::Command::Say("hi".into())).unwrap(), v1::Command::Say("hi".into()));
+    assert_eq!(v1::Command::Stop.encoded_bits(None), 4);
+
+    match convert::<_, v1::Command>(&v2::Command::Jump) {
+        Err(DeltaError::InvalidVariant { tag: 3, ref path }) => assert_eq!(path.to_string(), "Command"),
+        other => panic!("Expected an invalid variant, got {:?}", other),
+    }
+
+    // Without `delta_tag_bits` the tag uses the fewest bits that fit
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    enum Sparse {
+        A,
+        #[delta_tag = 12]
+        B,
+        C,
+    }
+    assert_eq!(Sparse::A.encoded_bits(None), 4);
+    assert_eq!(convert::<_, Sparse>(&Sparse::C).unwrap(), Sparse::C);
+}
+
+#[test]
+fn c_like_enums() {
+    #[derive(Debug, DeltaEncode, PartialEq, Clone, Copy)]
+    enum Kind {
+        Grass,
+        Stone,
+        Water,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone, Copy)]
+    #[delta_discriminant]
+    #[repr(i16)]
+    enum Block {
+        Air = -1,
+        Dirt = 10,
+        Sand,
+        Glass = 40,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone, Copy)]
+    #[delta_discriminant(min = 0, max = 255)]
+    #[repr(u8)]
+    enum Opcode {
+        Nop = 0,
+        Jump = 0x20,
+        Halt = 0xFF,
+    }
+
+    #[derive(Debug, DeltaEncode, PartialEq, Clone)]
+    struct Chunk {
+        kind: Kind,
+        blocks: [Block; 4],
+        op: Opcode,
+    }
+
+    assert_eq!(round_trip(&Kind::Water, None), 2);
+    assert_eq!(round_trip(&Kind::Water, Some(&Kind::Water)), 1);
+    assert_eq!(round_trip(&Kind::Grass, Some(&Kind::Water)), 1 + 2);
+
+    // Discriminants from -1 to 40 need 6 bits
+    assert_eq!(round_trip(&Block::Sand, None), 6);
+    assert_eq!(round_trip(&Block::Air, Some(&Block::Glass)), 1 + 6);
+    assert_eq!(round_trip(&Opcode::Jump, None), 8);
+
+    // The value sent is the discriminant minus the smallest one
+    let mut output = bitio::Writer::new(vec![]);
+    Block::Sand.encode(None, &mut output).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    assert_eq!(r.read_unsigned(6).unwrap() as i64, Block::Sand as i64 - Block::Air as i64);
+
+    let chunk = Chunk {
+        kind: Kind::Stone,
+        blocks: [Block::Dirt, Block::Air, Block::Sand, Block::Glass],
+        op: Opcode::Halt,
+    };
+    assert_eq!(round_trip(&chunk, None), 2 + 4 * 6 + 8);
+    let mut next = chunk.clone();
+    next.blocks[2] = Block::Dirt;
+    round_trip(&next, Some(&chunk));
+    assert_eq!(round_trip(&chunk, Some(&chunk)), 3);
+
+    // Unknown values are rejected
+    let mut output = bitio::Writer::new(vec![]);
+    output.write_unsigned(7, 6).unwrap();
+    let data = output.finish().unwrap();
+    let mut r = bitio::Reader::new(std::io::Cursor::new(data));
+    match Block::decode(None, &mut r) {
+        Err(delta_encode::DeltaError::InvalidVariant { tag: 7, .. }) => {},
+        other => panic!("Expected an invalid variant, got {:?}", other),
+    }
+}